@@ -0,0 +1,32 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use minecraft_parser::{parse_handshake, VarInt};
+use std::hint::black_box;
+
+const HANDSHAKE: &[u8] = b"\x13\x00\xf2\x05\x0c\x31\x32\x33\x2e\x34\x35\x2e\x36\x37\x2e\x38\x39\x63\xdd\x02";
+
+const VARINTS: &[(&str, &[u8])] = &[
+    ("1_byte", b"\x16"),
+    ("2_bytes", b"\xf2\x05"),
+    ("3_bytes", b"\xdd\xc7\x01"),
+    ("4_bytes", b"\xff\xff\xff\x07"),
+    ("5_bytes", b"\xff\xff\xff\xff\x0f"),
+];
+
+fn bench_parse_handshake(c: &mut Criterion) {
+    c.bench_function("parse_handshake", |b| {
+        b.iter(|| parse_handshake(&mut black_box(HANDSHAKE)).unwrap())
+    });
+}
+
+fn bench_parse_varint(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_varint");
+    for (name, bytes) in VARINTS {
+        group.bench_with_input(BenchmarkId::from_parameter(name), bytes, |b, bytes| {
+            b.iter(|| VarInt::decode(&mut black_box(*bytes)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse_handshake, bench_parse_varint);
+criterion_main!(benches);
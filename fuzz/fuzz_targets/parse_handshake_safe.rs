@@ -0,0 +1,10 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+extern crate minecraft_parser;
+use minecraft_parser::parse_handshake_safe;
+
+// any panic inside is a bug: the contract is to return Err instead
+fuzz_target!(|data: &[u8]| {
+    let _ = parse_handshake_safe(data);
+});
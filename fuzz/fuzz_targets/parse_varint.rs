@@ -0,0 +1,10 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+extern crate minecraft_parser;
+use minecraft_parser::parse_varint;
+
+fuzz_target!(|data: &[u8]| {
+    let mut data = data;
+    let _ = parse_varint(&mut data);
+});
@@ -0,0 +1,243 @@
+use bytes::Buf;
+use serde::de::Error as _;
+use serde_json::Value;
+
+use crate::nbt::{parse_nbt_network, NbtTag};
+use crate::{parse_string_n, MinecraftParseError};
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TextComponent {
+    text: String,
+    bold: bool,
+    italic: bool,
+    color: Option<String>,
+    extra: Vec<TextComponent>,
+}
+
+impl TextComponent {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn bold(&self) -> bool {
+        self.bold
+    }
+
+    pub fn italic(&self) -> bool {
+        self.italic
+    }
+
+    pub fn color(&self) -> Option<&str> {
+        self.color.as_deref()
+    }
+
+    pub fn extra(&self) -> &[TextComponent] {
+        &self.extra
+    }
+
+    /// Concatenates the text of this component and all of its children,
+    /// dropping formatting.
+    pub fn to_plain_string(&self) -> String {
+        let mut out = String::new();
+        self.push_plain(&mut out);
+        out
+    }
+
+    fn push_plain(&self, out: &mut String) {
+        out.push_str(&self.text);
+        for child in &self.extra {
+            child.push_plain(out);
+        }
+    }
+
+    pub(crate) fn from_value(value: &Value) -> Result<TextComponent, MinecraftParseError> {
+        match value {
+            Value::String(s) => Ok(TextComponent { text: s.clone(), ..Default::default() }),
+            // the first element is the parent, the rest are its children
+            Value::Array(parts) => {
+                let (first, rest) = parts.split_first().ok_or_else(|| {
+                    MinecraftParseError::InvalidJson(serde_json::Error::custom("empty text component array"))
+                })?;
+                let mut parent = TextComponent::from_value(first)?;
+                for part in rest {
+                    parent.extra.push(TextComponent::from_value(part)?);
+                }
+                Ok(parent)
+            }
+            Value::Object(fields) => {
+                let flag = |name| fields.get(name).and_then(Value::as_bool).unwrap_or(false);
+                let extra = match fields.get("extra") {
+                    Some(Value::Array(parts)) => parts.iter().map(TextComponent::from_value).collect::<Result<_, _>>()?,
+                    _ => Vec::new(),
+                };
+                Ok(TextComponent {
+                    text: fields.get("text").and_then(Value::as_str).unwrap_or_default().to_string(),
+                    bold: flag("bold"),
+                    italic: flag("italic"),
+                    color: fields.get("color").and_then(Value::as_str).map(str::to_string),
+                    extra,
+                })
+            }
+            // vanilla accepts numbers and booleans as literal text
+            Value::Number(_) | Value::Bool(_) => Ok(TextComponent { text: value.to_string(), ..Default::default() }),
+            Value::Null => Ok(TextComponent::default()),
+        }
+    }
+
+    /// The 1.20.3+ NBT counterpart of `from_value`, with flags stored as
+    /// bytes.
+    fn from_nbt(tag: &NbtTag) -> Result<TextComponent, MinecraftParseError> {
+        match tag {
+            NbtTag::String(s) => Ok(TextComponent { text: s.clone(), ..Default::default() }),
+            NbtTag::List(parts) => {
+                let (first, rest) = parts.split_first().ok_or(MinecraftParseError::InvalidTextComponent)?;
+                let mut parent = TextComponent::from_nbt(first)?;
+                for part in rest {
+                    parent.extra.push(TextComponent::from_nbt(part)?);
+                }
+                Ok(parent)
+            }
+            NbtTag::Compound(fields) => {
+                let field = |name| fields.iter().find(|(k, _)| k == name).map(|(_, v)| v);
+                let flag = |name| matches!(field(name), Some(NbtTag::Byte(b)) if *b != 0);
+                let string = |name| match field(name) {
+                    Some(NbtTag::String(s)) => Some(s.clone()),
+                    _ => None,
+                };
+                let extra = match field("extra") {
+                    Some(NbtTag::List(parts)) => parts.iter().map(TextComponent::from_nbt).collect::<Result<_, _>>()?,
+                    _ => Vec::new(),
+                };
+                Ok(TextComponent {
+                    text: string("text").unwrap_or_default(),
+                    bold: flag("bold"),
+                    italic: flag("italic"),
+                    color: string("color"),
+                    extra,
+                })
+            }
+            NbtTag::Byte(v) => Ok(TextComponent { text: v.to_string(), ..Default::default() }),
+            NbtTag::Short(v) => Ok(TextComponent { text: v.to_string(), ..Default::default() }),
+            NbtTag::Int(v) => Ok(TextComponent { text: v.to_string(), ..Default::default() }),
+            NbtTag::Long(v) => Ok(TextComponent { text: v.to_string(), ..Default::default() }),
+            NbtTag::Float(v) => Ok(TextComponent { text: v.to_string(), ..Default::default() }),
+            NbtTag::Double(v) => Ok(TextComponent { text: v.to_string(), ..Default::default() }),
+            _ => Err(MinecraftParseError::InvalidTextComponent),
+        }
+    }
+}
+
+pub fn parse_text_component(json: &str) -> Result<TextComponent, MinecraftParseError> {
+    let value: Value = serde_json::from_str(json).map_err(MinecraftParseError::InvalidJson)?;
+    TextComponent::from_value(&value)
+}
+
+/// Parses the body of the play-state Disconnect, after its id. The reason is
+/// NBT from 1.20.3 (protocol 765) and a JSON string before.
+pub fn parse_play_disconnect(buf: &mut dyn Buf, protocol_version: i32) -> Result<TextComponent, MinecraftParseError> {
+    if protocol_version >= 765 {
+        TextComponent::from_nbt(&parse_nbt_network(buf)?)
+    } else {
+        parse_text_component(&parse_string_n(buf)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod parse_text_component {
+        use crate::*;
+
+        #[test]
+        fn parse_bare_string() {
+            let val = parse_text_component(r#""Server closed""#).unwrap();
+            assert_eq!(val.text(), "Server closed");
+            assert!(val.extra().is_empty());
+        }
+
+        #[test]
+        fn parse_object() {
+            let val = parse_text_component(r#"{"text":"Hello ","bold":true,"color":"gold","extra":[{"text":"world","italic":true},"!"]}"#).unwrap();
+            assert_eq!(val.text(), "Hello ");
+            assert!(val.bold());
+            assert!(!val.italic());
+            assert_eq!(val.color(), Some("gold"));
+            assert_eq!(val.extra().len(), 2);
+            assert!(val.extra()[0].italic());
+            assert_eq!(val.extra()[1].text(), "!");
+            assert_eq!(val.to_plain_string(), "Hello world!");
+        }
+
+        #[test]
+        fn parse_array() {
+            let val = parse_text_component(r#"[{"text":"You are ","color":"red"},{"text":"banned","bold":true},"."]"#).unwrap();
+            assert_eq!(val.color(), Some("red"));
+            assert_eq!(val.extra().len(), 2);
+            assert!(val.extra()[0].bold());
+            assert_eq!(val.to_plain_string(), "You are banned.");
+        }
+
+        #[test]
+        fn parse_nested_plain_string() {
+            let val = parse_text_component(r#"{"text":"","extra":[{"text":"a","extra":["b",["c","d"]]},"e"]}"#).unwrap();
+            assert_eq!(val.to_plain_string(), "abcde");
+        }
+
+        #[test]
+        fn parse_empty_array() {
+            let val = parse_text_component("[]").err().unwrap();
+            assert!(matches!(val, MinecraftParseError::InvalidJson(_)));
+        }
+
+        #[test]
+        fn parse_malformed_json() {
+            let val = parse_text_component(r#"{"text":"#).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::InvalidJson(_)));
+        }
+    }
+
+    mod parse_play_disconnect {
+        use crate::*;
+
+        #[test]
+        fn parse_json_before_1_20_3() {
+            let mut buf = &b"\x1d{\"text\":\"Kicked\",\"bold\":true}"[..];
+            let val = parse_play_disconnect(&mut buf, 764).unwrap();
+            assert_eq!(val.text(), "Kicked");
+            assert!(val.bold());
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn parse_nbt_string_from_1_20_3() {
+            let mut buf = &b"\x08\x00\x06Kicked"[..];
+            let val = parse_play_disconnect(&mut buf, 765).unwrap();
+            assert_eq!(val.text(), "Kicked");
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn parse_nbt_compound() {
+            let mut buf = &b"\x0a\x08\x00\x04text\x00\x04Bye \x01\x00\x04bold\x01\x08\x00\x05color\x00\x03red\x09\x00\x05extra\x08\x00\x00\x00\x01\x00\x03now\x00"[..];
+            let val = parse_play_disconnect(&mut buf, 767).unwrap();
+            assert_eq!(val.text(), "Bye ");
+            assert!(val.bold());
+            assert!(!val.italic());
+            assert_eq!(val.color(), Some("red"));
+            assert_eq!(val.to_plain_string(), "Bye now");
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn reject_json_string_on_1_20_3() {
+            let mut buf = &b"\x08\"Kicked\""[..];
+            assert!(parse_play_disconnect(&mut buf, 765).is_err());
+        }
+
+        #[test]
+        fn reject_non_text_tag() {
+            let mut buf = &b"\x07\x00\x00\x00\x01\x2a"[..];
+            let val = parse_play_disconnect(&mut buf, 765).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::InvalidTextComponent));
+        }
+    }
+}
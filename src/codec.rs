@@ -0,0 +1,94 @@
+use bytes::BytesMut;
+use tokio_util::codec::Decoder;
+
+use crate::{check_packet_len, parse_handshake, peek_packet_length, Handshake, MinecraftParseError, MAX_PACKET_LEN};
+
+#[derive(Debug, Default)]
+pub struct HandshakeCodec;
+
+impl HandshakeCodec {
+    pub fn new() -> HandshakeCodec {
+        HandshakeCodec
+    }
+}
+
+impl Decoder for HandshakeCodec {
+    type Item = Handshake;
+    type Error = MinecraftParseError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Handshake>, MinecraftParseError> {
+        let (body_len, header_len) = match peek_packet_length(src)? {
+            Some(lens) => lens,
+            None => return Ok(None),
+        };
+        // fail before buffering a body that could never be accepted
+        check_packet_len(body_len, MAX_PACKET_LEN)?;
+        let frame_len = header_len.saturating_add(body_len);
+        if src.len() < frame_len {
+            return Ok(None);
+        }
+
+        let mut frame = src.split_to(frame_len);
+        parse_handshake(&mut frame).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod decode {
+        use crate::codec::*;
+        use crate::NextState;
+        use tokio_util::codec::Decoder;
+
+        use crate::test_util::SAMPLE_HANDSHAKE as PACKET;
+
+        #[test]
+        fn yield_only_after_final_chunk() {
+            let mut codec = HandshakeCodec::new();
+            let mut src = BytesMut::new();
+
+            src.extend_from_slice(&PACKET[..7]);
+            assert!(codec.decode(&mut src).unwrap().is_none());
+            assert_eq!(src.len(), 7);
+
+            src.extend_from_slice(&PACKET[7..]);
+            let val = codec.decode(&mut src).unwrap().unwrap();
+            assert_eq!(val.address(), "123.45.67.89");
+            assert!(src.is_empty());
+        }
+
+        #[test]
+        fn wait_for_length_prefix() {
+            let mut codec = HandshakeCodec::new();
+            let mut src = BytesMut::from(&b"\xf2"[..]);
+            assert!(codec.decode(&mut src).unwrap().is_none());
+        }
+
+        #[test]
+        fn consume_exactly_one_packet() {
+            let mut codec = HandshakeCodec::new();
+            let mut src = BytesMut::from(PACKET);
+            src.extend_from_slice(b"\x01\x00");
+
+            let val = codec.decode(&mut src).unwrap().unwrap();
+            assert_eq!(val.next_state(), NextState::Login);
+            assert_eq!(&src[..], b"\x01\x00");
+        }
+
+        #[test]
+        fn reject_oversized_packet_without_buffering() {
+            let mut codec = HandshakeCodec::new();
+            let mut src = BytesMut::from(&b"\x80\x80\x80\x01"[..]);
+            let val = codec.decode(&mut src).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::PacketTooLarge { len: 2097152, .. }));
+        }
+
+        #[test]
+        fn reject_malformed_packet() {
+            let mut codec = HandshakeCodec::new();
+            let mut src = BytesMut::from(&b"\x01\x01"[..]);
+            let val = codec.decode(&mut src).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::NotHandshake));
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use bytes::Buf;
+use flate2::read::ZlibDecoder;
+use std::io::Read;
+
+use crate::{check_packet_len, parse_varint_u32, MinecraftParseError, MAX_PACKET_LEN};
+
+// vanilla refuses to inflate packets larger than 2^23 bytes
+const MAX_UNCOMPRESSED_LEN: usize = 8388608;
+
+/// Reads one `[len][data_len][payload]` frame sent after Set Compression and
+/// returns the packet id and data, inflating the payload when `data_len` is
+/// non-zero. Only meaningful for a non-negative `threshold`.
+pub fn decode_compressed_frame(buf: &mut dyn Buf, threshold: i32) -> Result<Vec<u8>, MinecraftParseError> {
+    let len = parse_varint_u32(buf)? as usize;
+    check_packet_len(len, MAX_PACKET_LEN)?;
+    if buf.remaining() < len {
+        return Err(MinecraftParseError::LengthNotMatch);
+    }
+    let mut frame = Buf::take(&mut *buf, len);
+
+    let data_len = parse_varint_u32(&mut frame)? as usize;
+    let payload = frame.copy_to_bytes(frame.remaining());
+    if data_len == 0 {
+        return Ok(payload.to_vec());
+    }
+    if data_len < threshold.max(0) as usize || data_len > MAX_UNCOMPRESSED_LEN {
+        return Err(MinecraftParseError::InvalidDataLength(data_len));
+    }
+
+    // read one byte past the declared size so an overlong stream is noticed
+    // without inflating all of it
+    let mut body = Vec::new();
+    ZlibDecoder::new(&payload[..])
+        .take(data_len as u64 + 1)
+        .read_to_end(&mut body)
+        .map_err(MinecraftParseError::Decompression)?;
+    if body.len() != data_len {
+        return Err(MinecraftParseError::LengthNotMatch);
+    }
+    Ok(body)
+}
+
+/// Frame layout in effect on a connection, switched by Set Compression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameDecoder {
+    Uncompressed,
+    Compressed { threshold: i32 },
+}
+
+impl FrameDecoder {
+    /// Picks the layout for a Set Compression threshold, where a negative
+    /// value turns compression off.
+    pub fn from_threshold(threshold: i32) -> FrameDecoder {
+        if threshold < 0 {
+            FrameDecoder::Uncompressed
+        } else {
+            FrameDecoder::Compressed { threshold }
+        }
+    }
+
+    /// Reads one frame and returns the packet id and data.
+    pub fn decode_frame(&self, buf: &mut dyn Buf) -> Result<Vec<u8>, MinecraftParseError> {
+        match *self {
+            FrameDecoder::Uncompressed => {
+                let len = parse_varint_u32(buf)? as usize;
+                check_packet_len(len, MAX_PACKET_LEN)?;
+                if buf.remaining() < len {
+                    return Err(MinecraftParseError::LengthNotMatch);
+                }
+                Ok(buf.copy_to_bytes(len).to_vec())
+            }
+            FrameDecoder::Compressed { threshold } => decode_compressed_frame(buf, threshold),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod decode_compressed_frame {
+        use crate::compression::*;
+        use crate::VarInt;
+        use flate2::write::ZlibEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        pub(super) fn compressed_frame(body: &[u8]) -> Vec<u8> {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(body).unwrap();
+            let payload = encoder.finish().unwrap();
+
+            let mut inner = Vec::new();
+            VarInt(body.len() as i32).encode(&mut inner);
+            inner.extend(payload);
+            let mut frame = Vec::new();
+            VarInt(inner.len() as i32).encode(&mut frame);
+            frame.extend(inner);
+            frame
+        }
+
+        #[test]
+        fn pass_through_uncompressed() {
+            let mut buf = &b"\x0a\x00\x01\x00\x00\x00\x00\x00\x00\x00\x2a\x00"[..];
+            let val = decode_compressed_frame(&mut buf, 256).unwrap();
+            assert_eq!(val, b"\x01\x00\x00\x00\x00\x00\x00\x00\x2a");
+            assert_eq!(buf, b"\x00");
+        }
+
+        #[test]
+        fn inflate_compressed() {
+            let body = [&b"\x00"[..], &[b'a'; 300][..]].concat();
+            let frame = compressed_frame(&body);
+            let val = decode_compressed_frame(&mut &frame[..], 256).unwrap();
+            assert_eq!(val, body);
+        }
+
+        #[test]
+        fn reject_compressed_below_threshold() {
+            let frame = compressed_frame(b"\x00small");
+            let val = decode_compressed_frame(&mut &frame[..], 256).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::InvalidDataLength(6)));
+        }
+
+        #[test]
+        fn reject_wrong_declared_length() {
+            let mut frame = compressed_frame(&[b'a'; 300]);
+            // declare 301 bytes (0xad 0x02) instead of 300 (0xac 0x02)
+            frame[1] = 0xad;
+            let val = decode_compressed_frame(&mut &frame[..], 256).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::LengthNotMatch));
+        }
+
+        #[test]
+        fn reject_corrupted_stream() {
+            let mut buf = &b"\x05\xac\x02\xde\xad\xbe"[..];
+            let val = decode_compressed_frame(&mut buf, 256).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::Decompression(_)));
+        }
+    }
+
+    mod frame_decoder {
+        use super::decode_compressed_frame::compressed_frame;
+        use crate::compression::*;
+        use crate::parse_set_compression;
+
+        #[test]
+        fn decode_uncompressed() {
+            let mut buf = &b"\x02\x01\x2a\x00"[..];
+            let val = FrameDecoder::Uncompressed.decode_frame(&mut buf).unwrap();
+            assert_eq!(val, b"\x01\x2a");
+            assert_eq!(buf, b"\x00");
+        }
+
+        #[test]
+        fn decode_compressed() {
+            let body = [&b"\x00"[..], &[b'a'; 300][..]].concat();
+            let frame = compressed_frame(&body);
+
+            let decoder = FrameDecoder::Compressed { threshold: 256 };
+            assert_eq!(decoder.decode_frame(&mut &frame[..]).unwrap(), body);
+            // small packets stay uncompressed behind a zero data length
+            assert_eq!(decoder.decode_frame(&mut &b"\x03\x00\x01\x2a"[..]).unwrap(), b"\x01\x2a");
+        }
+
+        #[test]
+        fn switch_on_set_compression() {
+            let threshold = parse_set_compression(&mut &b"\x03\x03\x80\x02"[..]).unwrap();
+            assert_eq!(FrameDecoder::from_threshold(threshold), FrameDecoder::Compressed { threshold: 256 });
+            assert_eq!(FrameDecoder::from_threshold(-1), FrameDecoder::Uncompressed);
+        }
+
+        #[test]
+        fn reject_truncated_uncompressed() {
+            let mut buf = &b"\x05\x01\x2a"[..];
+            let val = FrameDecoder::Uncompressed.decode_frame(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::LengthNotMatch));
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use aes::Aes128;
+use cfb8::cipher::generic_array::GenericArray;
+use cfb8::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+
+/// AES-128-CFB8 state for one connection after Encryption Response. Each
+/// direction keeps its own feedback register, so data may be passed in
+/// chunks of any size.
+pub struct CipherStream {
+    encryptor: cfb8::Encryptor<Aes128>,
+    decryptor: cfb8::Decryptor<Aes128>,
+}
+
+impl CipherStream {
+    /// The shared secret serves as both key and IV.
+    pub fn new(shared_secret: &[u8; 16]) -> CipherStream {
+        CipherStream::with_key_iv(shared_secret, shared_secret)
+    }
+
+    fn with_key_iv(key: &[u8; 16], iv: &[u8; 16]) -> CipherStream {
+        CipherStream {
+            encryptor: cfb8::Encryptor::new(key.into(), iv.into()),
+            decryptor: cfb8::Decryptor::new(key.into(), iv.into()),
+        }
+    }
+
+    pub fn encrypt(&mut self, data: &mut [u8]) {
+        // CFB8 works on one-byte blocks
+        for byte in data.iter_mut() {
+            self.encryptor.encrypt_block_mut(GenericArray::from_mut_slice(core::slice::from_mut(byte)));
+        }
+    }
+
+    pub fn decrypt(&mut self, data: &mut [u8]) {
+        for byte in data.iter_mut() {
+            self.decryptor.decrypt_block_mut(GenericArray::from_mut_slice(core::slice::from_mut(byte)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod cipher_stream {
+        use crate::encryption::*;
+
+        // NIST SP 800-38A, F.3.7 CFB8-AES128
+        const KEY: [u8; 16] = *b"\x2b\x7e\x15\x16\x28\xae\xd2\xa6\xab\xf7\x15\x88\x09\xcf\x4f\x3c";
+        const IV: [u8; 16] = *b"\x00\x01\x02\x03\x04\x05\x06\x07\x08\x09\x0a\x0b\x0c\x0d\x0e\x0f";
+        const PLAINTEXT: &[u8] = b"\x6b\xc1\xbe\xe2\x2e\x40\x9f\x96\xe9\x3d\x7e\x11\x73\x93\x17\x2a\xae\x2d";
+        const CIPHERTEXT: &[u8] = b"\x3b\x79\x42\x4c\x9c\x0d\xd4\x36\xba\xce\x9e\x0e\xd4\x58\x6a\x4f\x32\xb9";
+
+        #[test]
+        fn encrypt_known_vector() {
+            let mut data = PLAINTEXT.to_vec();
+            CipherStream::with_key_iv(&KEY, &IV).encrypt(&mut data);
+            assert_eq!(data, CIPHERTEXT);
+        }
+
+        #[test]
+        fn decrypt_known_vector() {
+            let mut data = CIPHERTEXT.to_vec();
+            CipherStream::with_key_iv(&KEY, &IV).decrypt(&mut data);
+            assert_eq!(data, PLAINTEXT);
+        }
+
+        #[test]
+        fn keep_state_across_chunks() {
+            let mut stream = CipherStream::with_key_iv(&KEY, &IV);
+            let mut data = PLAINTEXT.to_vec();
+            let (first, second) = data.split_at_mut(7);
+            stream.encrypt(first);
+            stream.encrypt(second);
+            assert_eq!(data, CIPHERTEXT);
+
+            let (first, second) = data.split_at_mut(1);
+            stream.decrypt(first);
+            stream.decrypt(second);
+            assert_eq!(data, PLAINTEXT);
+        }
+
+        #[test]
+        fn round_trip_with_shared_secret() {
+            let mut client = CipherStream::new(&KEY);
+            let mut server = CipherStream::new(&KEY);
+            let mut data = crate::test_util::SAMPLE_HANDSHAKE.to_vec();
+            client.encrypt(&mut data);
+            assert_ne!(&data[..], crate::test_util::SAMPLE_HANDSHAKE);
+            server.decrypt(&mut data);
+            assert_eq!(&data[..], crate::test_util::SAMPLE_HANDSHAKE);
+        }
+    }
+}
@@ -1,218 +1,5428 @@
-use bytes::Buf;
-use std::str;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use core::convert::TryFrom;
+use core::fmt;
+use core::str;
+#[cfg(feature = "std")]
+use std::io::{self, Read};
 use thiserror::Error;
 
-const VARINT_MAX_BYTES: usize = 5;
+use nbt::{parse_nbt_named, parse_nbt_network, NbtTag};
+
+// tracing calls vanish entirely without the `tracing` feature
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::trace!($($arg)*);
+    };
+}
+
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::warn!($($arg)*);
+    };
+}
+
+macro_rules! warn_on_err {
+    ($res:expr, $msg:literal) => {{
+        let res = $res;
+        #[cfg(feature = "tracing")]
+        if let Err(e) = &res {
+            tracing::warn!(error = %e, $msg);
+        }
+        res
+    }};
+}
+
+mod versions;
+pub mod nbt;
+
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+
+pub use versions::protocol_name;
+
+#[cfg(feature = "compression")]
+mod compression;
+
+#[cfg(feature = "compression")]
+pub use compression::{decode_compressed_frame, FrameDecoder};
+
+#[cfg(feature = "encryption")]
+mod encryption;
+
+#[cfg(feature = "encryption")]
+pub use encryption::CipherStream;
+
+#[cfg(feature = "serde")]
+mod chat;
+
+#[cfg(feature = "serde")]
+pub use chat::{parse_play_disconnect, parse_text_component, TextComponent};
+
+#[cfg(feature = "serde")]
+mod status;
+
+#[cfg(feature = "serde")]
+pub use status::{parse_status_response, PlayerSample, StatusResponse};
+
+#[cfg(feature = "tokio")]
+mod codec;
+
+#[cfg(feature = "tokio")]
+pub use codec::HandshakeCodec;
+
+pub const VARINT_MAX_BYTES: usize = 5;
+const VARLONG_MAX_BYTES: usize = 10;
+// 32767 UTF-16 code units, each taking at most 3 bytes in UTF-8
+const STRING_MAX_BYTES: usize = 32767 * 3;
+const HANDSHAKE_ADDRESS_MAX_BYTES: usize = 255;
+const USERNAME_MAX_CHARS: usize = 16;
+const COOKIE_MAX_BYTES: usize = 5120;
+// far above any legitimate count, low enough to stop allocation bombs
+const ARRAY_MAX_LEN: i32 = 1 << 20;
+/// The largest packet length vanilla accepts, the 3-byte VarInt maximum.
+pub const MAX_PACKET_LEN: usize = 2097151;
+
+#[derive(Error, Debug)]
+pub enum MinecraftParseError {
+    #[error("VarInt is corrupted")]
+    InvalidVarInt,
+    #[error("VarInt does not fit in 32 bits")]
+    VarIntMalformed,
+    #[error("VarInt is encoded in more bytes than needed")]
+    VarIntNonCanonical,
+    #[error("VarLong is corrupted")]
+    InvalidVarLong,
+    #[error("VarLong does not fit in 64 bits")]
+    VarLongMalformed,
+    #[error("Unexpected end of buffer")]
+    UnexpectedEof,
+    #[error("Packet ended inside the {0} field")]
+    TruncatedField(&'static str),
+    #[error("Byte-encoded string is corrupted")]
+    InvalidStringEncoding(#[from] str::Utf8Error),
+    #[error("Byte-encoded string length is not sufficient")]
+    StringTooShort,
+    #[error("Byte-encoded string is longer than {max} bytes")]
+    StringTooLong { max: usize },
+    #[error("String is longer than {max} characters")]
+    StringTooManyChars { max: usize },
+    #[error("Byte array length is not sufficient")]
+    ArrayTooShort,
+    #[error("Packet length does not match its actual payload")]
+    LengthNotMatch,
+    #[error("This packet is not for handshaking")]
+    NotHandshake,
+    #[error("Unexpected packet id {0:#04x}")]
+    UnexpectedPacketId(i32),
+    #[cfg(feature = "std")]
+    #[error("I/O error while reading packet")]
+    Io(#[from] io::Error),
+    #[error("This packet is not a legacy server list ping")]
+    NotLegacyPing,
+    #[error("UTF-16 encoded string is corrupted")]
+    InvalidUtf16String,
+    #[cfg(feature = "std")]
+    #[error("Compressed packet could not be inflated")]
+    Decompression(#[source] io::Error),
+    #[error("Declared uncompressed length {0} is out of range")]
+    InvalidDataLength(usize),
+    #[error("Unknown next state {0}")]
+    InvalidNextState(i32),
+    #[error("Handshake asks for the {0} state, not the one this flow expects")]
+    UnexpectedNextState(NextState),
+    #[error("Boolean byte {0:#04x} is neither 0x00 nor 0x01")]
+    InvalidBool(u8),
+    #[error("Array count {0} is out of range")]
+    ArrayTooLarge(i32),
+    #[error("Protocol version {0} is not supported for this packet")]
+    UnsupportedProtocolVersion(i32),
+    #[error("Invalid identifier {0:?}")]
+    InvalidIdentifier(String),
+    #[error("Declared packet length {len} exceeds the maximum of {max}")]
+    PacketTooLarge { len: usize, max: usize },
+    #[error("Unknown NBT tag type {0}")]
+    InvalidNbtTag(u8),
+    #[error("NBT length {0} is negative")]
+    NegativeNbtLength(i32),
+    #[error("NBT string is corrupted")]
+    InvalidNbtString,
+    #[error("NBT is nested too deeply")]
+    NbtTooDeep,
+    #[error("Cookie payload of {0} bytes exceeds the 5120 byte limit")]
+    CookieTooLarge(usize),
+    #[error("Favicon is not a base64 PNG data URL")]
+    InvalidFavicon,
+    #[error("NBT tag is not a text component")]
+    InvalidTextComponent,
+    #[cfg(feature = "serde")]
+    #[error("JSON payload is malformed")]
+    InvalidJson(#[source] serde_json::Error),
+    #[error("{source} at byte {offset}")]
+    AtOffset {
+        offset: usize,
+        #[source]
+        source: Box<MinecraftParseError>,
+    },
+}
+
+impl MinecraftParseError {
+    /// The byte offset recorded by [`ParseCtx`], if any.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            MinecraftParseError::AtOffset { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// Strips the offset added by [`ParseCtx`], leaving the underlying error.
+    pub fn into_inner(self) -> MinecraftParseError {
+        match self {
+            MinecraftParseError::AtOffset { source, .. } => *source,
+            e => e,
+        }
+    }
+}
+
+// io::Error and serde_json::Error are not PartialEq, so those variants
+// compare by error kind or category and position instead
+impl PartialEq for MinecraftParseError {
+    fn eq(&self, other: &Self) -> bool {
+        use MinecraftParseError::*;
+        match (self, other) {
+            (InvalidVarInt, InvalidVarInt)
+            | (VarIntMalformed, VarIntMalformed)
+            | (VarIntNonCanonical, VarIntNonCanonical)
+            | (InvalidVarLong, InvalidVarLong)
+            | (VarLongMalformed, VarLongMalformed)
+            | (UnexpectedEof, UnexpectedEof)
+            | (StringTooShort, StringTooShort)
+            | (ArrayTooShort, ArrayTooShort)
+            | (LengthNotMatch, LengthNotMatch)
+            | (NotHandshake, NotHandshake)
+            | (NotLegacyPing, NotLegacyPing)
+            | (InvalidUtf16String, InvalidUtf16String)
+            | (InvalidNbtString, InvalidNbtString)
+            | (NbtTooDeep, NbtTooDeep)
+            | (InvalidFavicon, InvalidFavicon)
+            | (InvalidTextComponent, InvalidTextComponent) => true,
+            (TruncatedField(a), TruncatedField(b)) => a == b,
+            (InvalidStringEncoding(a), InvalidStringEncoding(b)) => a == b,
+            (StringTooLong { max: a }, StringTooLong { max: b })
+            | (StringTooManyChars { max: a }, StringTooManyChars { max: b })
+            | (InvalidDataLength(a), InvalidDataLength(b))
+            | (CookieTooLarge(a), CookieTooLarge(b)) => a == b,
+            (UnexpectedPacketId(a), UnexpectedPacketId(b))
+            | (InvalidNextState(a), InvalidNextState(b))
+            | (ArrayTooLarge(a), ArrayTooLarge(b))
+            | (UnsupportedProtocolVersion(a), UnsupportedProtocolVersion(b))
+            | (NegativeNbtLength(a), NegativeNbtLength(b)) => a == b,
+            (InvalidBool(a), InvalidBool(b)) | (InvalidNbtTag(a), InvalidNbtTag(b)) => a == b,
+            (InvalidIdentifier(a), InvalidIdentifier(b)) => a == b,
+            (UnexpectedNextState(a), UnexpectedNextState(b)) => a == b,
+            (PacketTooLarge { len: a, max: m }, PacketTooLarge { len: b, max: n }) => a == b && m == n,
+            (AtOffset { offset: a, source: e }, AtOffset { offset: b, source: f }) => a == b && e == f,
+            #[cfg(feature = "std")]
+            (Io(a), Io(b)) | (Decompression(a), Decompression(b)) => a.kind() == b.kind(),
+            #[cfg(feature = "serde")]
+            (InvalidJson(a), InvalidJson(b)) => {
+                a.classify() == b.classify() && a.line() == b.line() && a.column() == b.column()
+            }
+            _ => false,
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NextState {
+    Status,
+    Login,
+    /// Sent since 1.20.5 (protocol 766) by a client another server moved
+    /// here with a Transfer packet. The connection continues in the login
+    /// state; servers may refuse it or check cookies stored before the move.
+    Transfer,
+}
+
+impl TryFrom<i32> for NextState {
+    type Error = MinecraftParseError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(NextState::Status),
+            2 => Ok(NextState::Login),
+            3 => Ok(NextState::Transfer),
+            _ => Err(MinecraftParseError::InvalidNextState(value)),
+        }
+    }
+}
+
+impl From<NextState> for i32 {
+    fn from(state: NextState) -> i32 {
+        match state {
+            NextState::Status => 1,
+            NextState::Login => 2,
+            NextState::Transfer => 3,
+        }
+    }
+}
+
+impl fmt::Display for NextState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            NextState::Status => "Status",
+            NextState::Login => "Login",
+            NextState::Transfer => "Transfer",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Handshake {
+    protocol_version: i32,
+    address: String,
+    port: u16,
+    next_state: NextState,
+}
+
+impl Handshake {
+    pub fn new(protocol_version: i32, address: impl Into<String>, port: u16, next_state: NextState) -> Handshake {
+        Handshake {
+            protocol_version,
+            address: address.into(),
+            port,
+            next_state,
+        }
+    }
+
+    pub fn builder() -> HandshakeBuilder {
+        HandshakeBuilder::new()
+    }
+
+    pub fn protocol_version(&self) -> i32 {
+        self.protocol_version
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn next_state(&self) -> NextState {
+        self.next_state
+    }
+
+    pub fn expected_next_packet(&self) -> ExpectedPacket {
+        match self.next_state {
+            NextState::Status => ExpectedPacket::StatusRequest,
+            NextState::Login => ExpectedPacket::LoginStart,
+            NextState::Transfer => ExpectedPacket::TransferLogin,
+        }
+    }
+
+    /// Splits the handshake into the arguments `new` takes, moving the
+    /// address out instead of cloning it.
+    pub fn into_parts(self) -> (i32, String, u16, NextState) {
+        (self.protocol_version, self.address, self.port, self.next_state)
+    }
+
+    /// Encodes the handshake as a complete packet, length prefix included.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = BytesMut::with_capacity(self.total_len());
+        encode_handshake(self, &mut out);
+        out.to_vec()
+    }
+
+    /// Size of the encoded packet body: the packet id and every field, but
+    /// not the outer length prefix.
+    pub fn wire_len(&self) -> usize {
+        varint_len(0x00)
+            + varint_len(self.protocol_version)
+            + varint_len(self.address.len() as i32)
+            + self.address.len()
+            + 2
+            + varint_len(self.next_state.into())
+    }
+
+    /// Size of the whole encoded packet, length prefix included.
+    pub fn total_len(&self) -> usize {
+        let body = self.wire_len();
+        varint_len(body as i32) + body
+    }
+
+    /// Whether the client arrived through a server transfer rather than a
+    /// fresh login; see [`NextState::Transfer`].
+    pub fn is_transfer(&self) -> bool {
+        self.next_state == NextState::Transfer
+    }
+
+    pub fn minecraft_version(&self) -> Option<&'static str> {
+        protocol_name(self.protocol_version)
+    }
+
+    pub fn forge_marker(&self) -> Option<ForgeMarker> {
+        match self.address.split('\0').nth(1)? {
+            "FML" => Some(ForgeMarker::Fml),
+            "FML2" => Some(ForgeMarker::Fml2),
+            marker if marker.starts_with("FORGE") => Some(ForgeMarker::Forge),
+            _ => None,
+        }
+    }
+
+    /// The hostname without any null-delimited suffix such as a Forge marker.
+    pub fn clean_address(&self) -> &str {
+        match self.address.find('\0') {
+            Some(end) => &self.address[..end],
+            None => &self.address,
+        }
+    }
+}
+
+/// The packet a client sends right after its handshake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpectedPacket {
+    StatusRequest,
+    LoginStart,
+    /// A Login Start from a client that arrived through a transfer.
+    TransferLogin,
+}
+
+/// Reasonable modern defaults: protocol 754 (1.16.5), an empty address, port
+/// 25565 and the status state.
+impl Default for Handshake {
+    fn default() -> Handshake {
+        Handshake {
+            protocol_version: 754,
+            address: String::new(),
+            port: 25565,
+            next_state: NextState::Status,
+        }
+    }
+}
+
+/// Suffix Forge clients append to the handshake address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForgeMarker {
+    /// `\0FML\0`, sent by 1.7-1.12 Forge
+    Fml,
+    /// `\0FML2\0`, sent by 1.13-1.17 Forge
+    Fml2,
+    /// `\0FORGE` followed by a network version, sent by newer Forge
+    Forge,
+}
+
+impl fmt::Display for Handshake {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Handshake(protocol={}, addr={}:{}, next={})",
+            self.protocol_version, self.address, self.port, self.next_state
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HandshakeBuilder {
+    protocol_version: i32,
+    address: String,
+    port: u16,
+    next_state: NextState,
+}
+
+impl HandshakeBuilder {
+    pub fn new() -> HandshakeBuilder {
+        HandshakeBuilder {
+            protocol_version: 754,
+            address: "localhost".to_string(),
+            port: 25565,
+            next_state: NextState::Status,
+        }
+    }
+
+    pub fn protocol_version(mut self, protocol_version: i32) -> HandshakeBuilder {
+        self.protocol_version = protocol_version;
+        self
+    }
+
+    pub fn address(mut self, address: impl Into<String>) -> HandshakeBuilder {
+        self.address = address.into();
+        self
+    }
+
+    pub fn port(mut self, port: u16) -> HandshakeBuilder {
+        self.port = port;
+        self
+    }
+
+    pub fn next_state(mut self, next_state: NextState) -> HandshakeBuilder {
+        self.next_state = next_state;
+        self
+    }
+
+    pub fn build(self) -> Handshake {
+        Handshake::new(self.protocol_version, self.address, self.port, self.next_state)
+    }
+}
+
+impl Default for HandshakeBuilder {
+    fn default() -> HandshakeBuilder {
+        HandshakeBuilder::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HandshakeRef<'a> {
+    protocol_version: i32,
+    address: &'a str,
+    port: u16,
+    next_state: NextState,
+}
+
+impl<'a> HandshakeRef<'a> {
+    pub fn protocol_version(&self) -> i32 {
+        self.protocol_version
+    }
+
+    pub fn address(&self) -> &'a str {
+        self.address
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn next_state(&self) -> NextState {
+        self.next_state
+    }
+
+    pub fn to_owned(&self) -> Handshake {
+        Handshake {
+            protocol_version: self.protocol_version,
+            address: self.address.to_string(),
+            port: self.port,
+            next_state: self.next_state,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LoginStart {
+    name: String,
+    uuid: Option<u128>,
+}
+
+impl LoginStart {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn uuid(&self) -> Option<u128> {
+        self.uuid
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Property {
+    name: String,
+    value: String,
+    signature: Option<String>,
+}
+
+impl Property {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn signature(&self) -> Option<&str> {
+        self.signature.as_deref()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LoginSuccess {
+    uuid: u128,
+    username: String,
+    properties: Vec<Property>,
+}
+
+impl LoginSuccess {
+    pub fn uuid(&self) -> u128 {
+        self.uuid
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    pub fn properties(&self) -> &[Property] {
+        &self.properties
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    x: i32,
+    y: i32,
+    z: i32,
+}
+
+impl Position {
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    pub fn y(&self) -> i32 {
+        self.y
+    }
+
+    pub fn z(&self) -> i32 {
+        self.z
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Identifier {
+    namespace: String,
+    path: String,
+}
+
+impl Identifier {
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl str::FromStr for Identifier {
+    type Err = MinecraftParseError;
+
+    /// Splits on the first `:`; a bare path is in the `minecraft` namespace.
+    fn from_str(s: &str) -> Result<Identifier, MinecraftParseError> {
+        let (namespace, path) = s.split_once(':').unwrap_or(("minecraft", s));
+        let valid_namespace = namespace.bytes().all(|b| matches!(b, b'a'..=b'z' | b'0'..=b'9' | b'.' | b'_' | b'-'));
+        let valid_path = path.bytes().all(|b| matches!(b, b'a'..=b'z' | b'0'..=b'9' | b'.' | b'_' | b'-' | b'/'));
+        if !valid_namespace || !valid_path {
+            return Err(MinecraftParseError::InvalidIdentifier(s.to_string()));
+        }
+        Ok(Identifier { namespace: namespace.to_string(), path: path.to_string() })
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.namespace, self.path)
+    }
+}
+
+/// A bitset backed by longs, bit `i` living in bit `i % 64` of word `i / 64`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitSet {
+    words: Vec<i64>,
+}
+
+impl BitSet {
+    /// Bits past the end of the backing longs are unset.
+    pub fn get(&self, index: usize) -> bool {
+        match self.words.get(index / 64) {
+            Some(word) => word >> (index % 64) & 1 != 0,
+            None => false,
+        }
+    }
+
+    pub fn words(&self) -> &[i64] {
+        &self.words
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct EncryptionRequest {
+    server_id: String,
+    public_key: Vec<u8>,
+    verify_token: Vec<u8>,
+    should_authenticate: Option<bool>,
+}
+
+impl EncryptionRequest {
+    pub fn server_id(&self) -> &str {
+        &self.server_id
+    }
+
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    pub fn verify_token(&self) -> &[u8] {
+        &self.verify_token
+    }
+
+    /// Only sent from 1.20.5 (protocol 766) on; `None` for older versions.
+    pub fn should_authenticate(&self) -> Option<bool> {
+        self.should_authenticate
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct EncryptionResponse {
+    shared_secret: Vec<u8>,
+    verify_token: Option<Vec<u8>>,
+    salt_signature: Option<(i64, Vec<u8>)>,
+}
+
+impl EncryptionResponse {
+    pub fn shared_secret(&self) -> &[u8] {
+        &self.shared_secret
+    }
+
+    /// `None` only when a 1.19-1.19.2 client signed the nonce instead.
+    pub fn verify_token(&self) -> Option<&[u8]> {
+        self.verify_token.as_deref()
+    }
+
+    /// The salt and signature a 1.19-1.19.2 client sends in place of the
+    /// verify token.
+    pub fn salt_signature(&self) -> Option<(i64, &[u8])> {
+        self.salt_signature.as_ref().map(|(salt, signature)| (*salt, &signature[..]))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ChatMessage {
+    message: String,
+    timestamp: i64,
+    salt: i64,
+    signature: Option<Vec<u8>>,
+}
+
+impl ChatMessage {
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Milliseconds since the Unix epoch, as set by the client.
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    pub fn salt(&self) -> i64 {
+        self.salt
+    }
+
+    /// `None` when the client did not sign the message.
+    pub fn signature(&self) -> Option<&[u8]> {
+        self.signature.as_deref()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PluginMessage {
+    channel: Identifier,
+    data: Vec<u8>,
+}
+
+impl PluginMessage {
+    pub fn channel(&self) -> &Identifier {
+        &self.channel
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LoginPluginRequest {
+    message_id: i32,
+    channel: Identifier,
+    data: Vec<u8>,
+}
+
+impl LoginPluginRequest {
+    pub fn message_id(&self) -> i32 {
+        self.message_id
+    }
+
+    pub fn channel(&self) -> &Identifier {
+        &self.channel
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LoginPluginResponse {
+    message_id: i32,
+    data: Option<Vec<u8>>,
+}
+
+impl LoginPluginResponse {
+    pub fn message_id(&self) -> i32 {
+        self.message_id
+    }
+
+    /// `None` when the client did not understand the request.
+    pub fn data(&self) -> Option<&[u8]> {
+        self.data.as_deref()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientInformation {
+    locale: String,
+    view_distance: i8,
+    chat_mode: i32,
+    chat_colors: bool,
+    skin_parts: u8,
+    main_hand: i32,
+    text_filtering: bool,
+    allow_listing: bool,
+}
+
+impl ClientInformation {
+    /// Such as `en_us`.
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// In chunks.
+    pub fn view_distance(&self) -> i8 {
+        self.view_distance
+    }
+
+    /// 0 for full chat, 1 for commands only, 2 for hidden.
+    pub fn chat_mode(&self) -> i32 {
+        self.chat_mode
+    }
+
+    pub fn chat_colors(&self) -> bool {
+        self.chat_colors
+    }
+
+    /// One bit per displayed skin layer, from the cape at bit 0 to the hat
+    /// at bit 6.
+    pub fn skin_parts(&self) -> u8 {
+        self.skin_parts
+    }
+
+    /// 0 for left, 1 for right.
+    pub fn main_hand(&self) -> i32 {
+        self.main_hand
+    }
+
+    pub fn text_filtering(&self) -> bool {
+        self.text_filtering
+    }
+
+    /// Whether the player may appear in the server list sample.
+    pub fn allow_listing(&self) -> bool {
+        self.allow_listing
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayerPosition {
+    x: f64,
+    y: f64,
+    z: f64,
+    on_ground: bool,
+}
+
+impl PlayerPosition {
+    pub fn x(&self) -> f64 {
+        self.x
+    }
+
+    /// The feet position, not the eyes.
+    pub fn y(&self) -> f64 {
+        self.y
+    }
+
+    pub fn z(&self) -> f64 {
+        self.z
+    }
+
+    pub fn on_ground(&self) -> bool {
+        self.on_ground
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayerPositionRotation {
+    x: f64,
+    y: f64,
+    z: f64,
+    yaw: f32,
+    pitch: f32,
+    on_ground: bool,
+}
+
+impl PlayerPositionRotation {
+    pub fn x(&self) -> f64 {
+        self.x
+    }
+
+    /// The feet position, not the eyes.
+    pub fn y(&self) -> f64 {
+        self.y
+    }
+
+    pub fn z(&self) -> f64 {
+        self.z
+    }
+
+    /// Degrees, not clamped to a single turn.
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    /// Degrees, from -90 looking up to 90 looking down.
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    pub fn on_ground(&self) -> bool {
+        self.on_ground
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slot {
+    item_id: Option<i32>,
+    count: i8,
+    nbt: Option<NbtTag>,
+}
+
+impl Slot {
+    /// `None` for an empty slot.
+    pub fn item_id(&self) -> Option<i32> {
+        self.item_id
+    }
+
+    pub fn count(&self) -> i8 {
+        self.count
+    }
+
+    pub fn nbt(&self) -> Option<&NbtTag> {
+        self.nbt.as_ref()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.item_id.is_none()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct KnownPack {
+    namespace: String,
+    id: String,
+    version: String,
+}
+
+impl KnownPack {
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CookieRequest {
+    key: Identifier,
+}
+
+impl CookieRequest {
+    pub fn key(&self) -> &Identifier {
+        &self.key
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CookieResponse {
+    key: Identifier,
+    payload: Option<Vec<u8>>,
+}
+
+impl CookieResponse {
+    pub fn key(&self) -> &Identifier {
+        &self.key
+    }
+
+    /// `None` when the client holds no cookie under this key.
+    pub fn payload(&self) -> Option<&[u8]> {
+        self.payload.as_deref()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LegacyPing {
+    protocol_version: u8,
+    hostname: String,
+    port: i32,
+}
+
+impl LegacyPing {
+    pub fn protocol_version(&self) -> u8 {
+        self.protocol_version
+    }
+
+    pub fn hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    pub fn port(&self) -> i32 {
+        self.port
+    }
+}
+
+pub trait Decodable: Sized {
+    fn decode(buf: &mut dyn Buf) -> Result<Self, MinecraftParseError>;
+}
+
+pub trait Encodable {
+    fn encode(&self, buf: &mut dyn BufMut);
+}
+
+/// Decodes any [`Decodable`] type, e.g. `decode::<Handshake>(&mut buf)`.
+pub fn decode<T: Decodable>(buf: &mut dyn Buf) -> Result<T, MinecraftParseError> {
+    T::decode(buf)
+}
+
+macro_rules! impl_primitive {
+    ($ty:ty, $parse:ident, $put:ident) => {
+        impl Decodable for $ty {
+            fn decode(buf: &mut dyn Buf) -> Result<Self, MinecraftParseError> {
+                $parse(buf)
+            }
+        }
+
+        impl Encodable for $ty {
+            fn encode(&self, buf: &mut dyn BufMut) {
+                buf.$put(*self);
+            }
+        }
+    };
+}
+
+impl_primitive!(i8, parse_byte, put_i8);
+impl_primitive!(u8, parse_ubyte, put_u8);
+impl_primitive!(u16, parse_ushort, put_u16);
+impl_primitive!(i16, parse_short, put_i16);
+impl_primitive!(i32, parse_int, put_i32);
+impl_primitive!(i64, parse_long, put_i64);
+impl_primitive!(f32, parse_float, put_f32);
+impl_primitive!(f64, parse_double, put_f64);
+impl_primitive!(u128, parse_uuid, put_u128);
+
+impl Decodable for bool {
+    fn decode(buf: &mut dyn Buf) -> Result<Self, MinecraftParseError> {
+        parse_bool(buf)
+    }
+}
+
+impl Encodable for bool {
+    fn encode(&self, buf: &mut dyn BufMut) {
+        buf.put_u8(*self as u8);
+    }
+}
+
+impl Decodable for String {
+    fn decode(buf: &mut dyn Buf) -> Result<Self, MinecraftParseError> {
+        parse_string_n(buf)
+    }
+}
+
+impl Encodable for String {
+    fn encode(&self, mut buf: &mut dyn BufMut) {
+        encode_string_n(self, &mut buf);
+    }
+}
+
+pub fn parse_byte(buf: &mut dyn Buf) -> Result<i8, MinecraftParseError> {
+    if buf.remaining() < 1 {
+        return Err(MinecraftParseError::UnexpectedEof);
+    }
+    let val = buf.get_i8();
+    Ok(val)
+}
+
+pub fn parse_ubyte(buf: &mut dyn Buf) -> Result<u8, MinecraftParseError> {
+    if buf.remaining() < 1 {
+        return Err(MinecraftParseError::UnexpectedEof);
+    }
+    let val = buf.get_u8();
+    Ok(val)
+}
+
+fn parse_ushort(buf: &mut dyn Buf) -> Result<u16, MinecraftParseError> {
+    if buf.remaining() < 2 {
+        return Err(MinecraftParseError::UnexpectedEof);
+    }
+    let val = buf.get_u16();
+    Ok(val)
+}
+
+pub fn parse_short(buf: &mut dyn Buf) -> Result<i16, MinecraftParseError> {
+    if buf.remaining() < 2 {
+        return Err(MinecraftParseError::UnexpectedEof);
+    }
+    let val = buf.get_i16();
+    Ok(val)
+}
+
+pub fn parse_int(buf: &mut dyn Buf) -> Result<i32, MinecraftParseError> {
+    if buf.remaining() < 4 {
+        return Err(MinecraftParseError::UnexpectedEof);
+    }
+    let val = buf.get_i32();
+    Ok(val)
+}
+
+pub fn parse_long(buf: &mut dyn Buf) -> Result<i64, MinecraftParseError> {
+    if buf.remaining() < 8 {
+        return Err(MinecraftParseError::UnexpectedEof);
+    }
+    let val = buf.get_i64();
+    Ok(val)
+}
+
+pub fn parse_float(buf: &mut dyn Buf) -> Result<f32, MinecraftParseError> {
+    if buf.remaining() < 4 {
+        return Err(MinecraftParseError::UnexpectedEof);
+    }
+    let val = buf.get_f32();
+    Ok(val)
+}
+
+pub fn parse_double(buf: &mut dyn Buf) -> Result<f64, MinecraftParseError> {
+    if buf.remaining() < 8 {
+        return Err(MinecraftParseError::UnexpectedEof);
+    }
+    let val = buf.get_f64();
+    Ok(val)
+}
+
+pub fn parse_bool(buf: &mut dyn Buf) -> Result<bool, MinecraftParseError> {
+    if buf.remaining() < 1 {
+        return Err(MinecraftParseError::UnexpectedEof);
+    }
+    match buf.get_u8() {
+        0x00 => Ok(false),
+        0x01 => Ok(true),
+        byte => Err(MinecraftParseError::InvalidBool(byte)),
+    }
+}
+
+pub fn parse_uuid(buf: &mut dyn Buf) -> Result<u128, MinecraftParseError> {
+    if buf.remaining() < 16 {
+        return Err(MinecraftParseError::UnexpectedEof);
+    }
+    let val = buf.get_u128();
+    Ok(val)
+}
+
+pub fn format_uuid(u: u128) -> String {
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        u >> 96,
+        (u >> 80) & 0xffff,
+        (u >> 64) & 0xffff,
+        (u >> 48) & 0xffff,
+        u & 0xffff_ffff_ffff,
+    )
+}
+
+pub fn parse_position(buf: &mut dyn Buf) -> Result<Position, MinecraftParseError> {
+    let val = parse_long(buf)?;
+    // x: 26 bits, z: 26 bits, y: 12 bits; arithmetic shifts sign-extend each field
+    let x = (val >> 38) as i32;
+    let y = (val << 52 >> 52) as i32;
+    let z = (val << 26 >> 38) as i32;
+    Ok(Position { x, y, z })
+}
+
+/// Reads a rotation stored as 1/256 of a full turn and returns it in degrees.
+pub fn parse_angle(buf: &mut dyn Buf) -> Result<f32, MinecraftParseError> {
+    Ok(parse_ubyte(buf)? as f32 * 360.0 / 256.0)
+}
+
+pub fn parse_bitset(buf: &mut dyn Buf) -> Result<BitSet, MinecraftParseError> {
+    let words = parse_array(buf, parse_long)?;
+    Ok(BitSet { words })
+}
+
+pub fn parse_identifier(buf: &mut dyn Buf) -> Result<Identifier, MinecraftParseError> {
+    parse_string_n(buf)?.parse()
+}
+
+// public for the fuzz targets
+#[doc(hidden)]
+pub fn parse_varint(buf: &mut dyn Buf) -> Result<i32, MinecraftParseError> {
+    let (v, _) = warn_on_err!(parse_varint_counted(buf), "invalid VarInt")?;
+    trace!(value = v, "read VarInt");
+    Ok(v)
+}
+
+/// Like [`parse_varint`], but also rejects overlong encodings such as
+/// `\x80\x00` for 0 with `VarIntNonCanonical`. Vanilla accepts them, so
+/// only use this where crafted packets should be flagged.
+pub fn parse_varint_strict(buf: &mut dyn Buf) -> Result<i32, MinecraftParseError> {
+    let (v, len) = parse_varint_counted(buf)?;
+    if len > varint_len(v) {
+        return Err(MinecraftParseError::VarIntNonCanonical);
+    }
+    Ok(v)
+}
+
+/// Reads a VarInt as the unsigned value of its 32 bits, for lengths and
+/// counts where a negative `i32` would wrap around when cast to `usize`.
+pub fn parse_varint_u32(buf: &mut dyn Buf) -> Result<u32, MinecraftParseError> {
+    parse_varint(buf).map(|v| v as u32)
+}
+
+fn parse_varint_counted(buf: &mut dyn Buf) -> Result<(i32, usize), MinecraftParseError> {
+    let mut bytes = core::iter::from_fn(|| if buf.has_remaining() { Some(buf.get_u8()) } else { None });
+    decode_varint_counted(&mut bytes)
+}
+
+/// Decodes a VarInt from a byte-at-a-time source that is not a `Buf`, with
+/// the same checks as [`parse_varint`]. Only the VarInt's own bytes are
+/// pulled from `iter`.
+pub fn decode_varint_iter<I: Iterator<Item = u8>>(iter: &mut I) -> Result<i32, MinecraftParseError> {
+    decode_varint_counted(iter).map(|(v, _)| v)
+}
+
+fn decode_varint_counted<I: Iterator<Item = u8>>(iter: &mut I) -> Result<(i32, usize), MinecraftParseError> {
+    let mut v: i32 = 0;
+    let mut bit_place: usize = 0;
+    let mut i: usize = 0;
+    let mut has_more = true;
+
+    while has_more {
+        if i == VARINT_MAX_BYTES {
+            return Err(MinecraftParseError::InvalidVarInt)
+        }
+        let byte = match iter.next() {
+            Some(byte) => byte,
+            None => return Err(MinecraftParseError::UnexpectedEof),
+        };
+        
+        has_more = byte & 0x80 != 0;
+        // the last byte may only carry the top 4 bits of an i32; anything
+        // above them would land at bit 32 or higher and be shifted out
+        if i == VARINT_MAX_BYTES - 1 && !has_more && byte & 0x70 != 0 {
+            return Err(MinecraftParseError::VarIntMalformed)
+        }
+        v |= ((byte as i32) & 0x7F) << bit_place;
+        bit_place += 7;
+        i += 1;
+    }
+
+    Ok((v, i))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VarInt(pub i32);
+
+impl VarInt {
+    pub fn decode(buf: &mut dyn Buf) -> Result<VarInt, MinecraftParseError> {
+        parse_varint(buf).map(VarInt)
+    }
+
+    pub fn encode(&self, buf: &mut impl BufMut) {
+        let (bytes, len) = write_varint_array(self.0);
+        buf.put_slice(&bytes[..len]);
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        varint_len(self.0)
+    }
+}
+
+/// Encodes a VarInt into a stack array, returning it with the number of
+/// bytes used; the rest of the array is zero.
+pub fn write_varint_array(value: i32) -> ([u8; VARINT_MAX_BYTES], usize) {
+    let mut out = [0u8; VARINT_MAX_BYTES];
+    let mut v = value as u32;
+    let mut len = 0;
+    while v & !0x7F != 0 {
+        out[len] = (v & 0x7F) as u8 | 0x80;
+        v >>= 7;
+        len += 1;
+    }
+    out[len] = v as u8;
+    (out, len + 1)
+}
+
+pub fn varint_len(value: i32) -> usize {
+    let mut v = value as u32;
+    let mut len = 1;
+    while v & !0x7F != 0 {
+        v >>= 7;
+        len += 1;
+    }
+    len
+}
+
+fn parse_varlong(buf: &mut dyn Buf) -> Result<i64, MinecraftParseError> {
+    let mut v: i64 = 0;
+    let mut bit_place: usize = 0;
+    let mut i: usize = 0;
+    let mut has_more = true;
+
+    while has_more {
+        if i == VARLONG_MAX_BYTES {
+            return Err(MinecraftParseError::InvalidVarLong)
+        }
+        if buf.remaining() == 0 {
+            return Err(MinecraftParseError::UnexpectedEof)
+        }
+        let byte = buf.get_u8();
+
+        has_more = byte & 0x80 != 0;
+        // the last byte may only carry the top bit of an i64
+        if i == VARLONG_MAX_BYTES - 1 && !has_more && byte & 0x7E != 0 {
+            return Err(MinecraftParseError::VarLongMalformed)
+        }
+        v |= ((byte as i64) & 0x7F) << bit_place;
+        bit_place += 7;
+        i += 1;
+    }
+
+    Ok(v)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VarLong(pub i64);
+
+impl VarLong {
+    pub fn decode(buf: &mut dyn Buf) -> Result<VarLong, MinecraftParseError> {
+        parse_varlong(buf).map(VarLong)
+    }
+
+    pub fn encode(&self, buf: &mut impl BufMut) {
+        let mut v = self.0 as u64;
+        while v & !0x7F != 0 {
+            buf.put_u8((v & 0x7F) as u8 | 0x80);
+            v >>= 7;
+        }
+        buf.put_u8(v as u8);
+    }
+}
+
+impl Decodable for VarInt {
+    fn decode(buf: &mut dyn Buf) -> Result<Self, MinecraftParseError> {
+        VarInt::decode(buf)
+    }
+}
+
+impl Encodable for VarInt {
+    fn encode(&self, mut buf: &mut dyn BufMut) {
+        VarInt::encode(self, &mut buf);
+    }
+}
+
+impl Decodable for VarLong {
+    fn decode(buf: &mut dyn Buf) -> Result<Self, MinecraftParseError> {
+        VarLong::decode(buf)
+    }
+}
+
+impl Encodable for VarLong {
+    fn encode(&self, mut buf: &mut dyn BufMut) {
+        VarLong::encode(self, &mut buf);
+    }
+}
+
+/// Reads a VarInt-prefixed UTF-8 string. A declared length above the protocol
+/// cap fails with `StringTooLong` before the buffer is looked at, while
+/// `StringTooShort` means the string is merely truncated.
+// public for the fuzz targets
+#[doc(hidden)]
+pub fn parse_string_n(buf: &mut dyn Buf) -> Result<String, MinecraftParseError> {
+    parse_string_n_capped(buf, STRING_MAX_BYTES)
+}
+
+fn parse_string_n_capped(buf: &mut dyn Buf, max_len: usize) -> Result<String, MinecraftParseError> {
+    let len = parse_varint_u32(buf)? as usize;
+    trace!(len, max_len, "declared string length");
+
+    if len > max_len {
+        warn!(len, max_len, "string length over the limit");
+        return Err(MinecraftParseError::StringTooLong { max: max_len });
+    }
+    if buf.remaining() < len {
+        warn!(len, remaining = buf.remaining(), "string truncated");
+        return Err(MinecraftParseError::StringTooShort);
+    }
+
+    let bytes = buf.copy_to_bytes(len);
+    let val: String = warn_on_err!(str::from_utf8(&bytes), "string is not valid UTF-8")?.to_string();
+    Ok(val)
+}
+
+/// Reads a string whose limit is given in characters, as the protocol states
+/// it, rejecting more than `max_chars` with `StringTooManyChars`. Characters
+/// are counted in UTF-16 code units like vanilla does, and the declared byte
+/// length is first held to the 3 bytes per unit UTF-8 can need.
+pub fn parse_string_max_chars(buf: &mut dyn Buf, max_chars: usize) -> Result<String, MinecraftParseError> {
+    let val = parse_string_n_capped(buf, max_chars.saturating_mul(3).min(STRING_MAX_BYTES))?;
+    if val.encode_utf16().count() > max_chars {
+        warn!(max_chars, "string has too many characters");
+        return Err(MinecraftParseError::StringTooManyChars { max: max_chars });
+    }
+    Ok(val)
+}
+
+fn parse_str_ref<'a>(buf: &mut &'a [u8], max_len: usize) -> Result<&'a str, MinecraftParseError> {
+    let len = parse_varint_u32(buf)? as usize;
+
+    if len > max_len {
+        return Err(MinecraftParseError::StringTooLong { max: max_len });
+    }
+    if buf.len() < len {
+        return Err(MinecraftParseError::StringTooShort);
+    }
+
+    let (bytes, rest) = buf.split_at(len);
+    *buf = rest;
+    Ok(str::from_utf8(bytes)?)
+}
+
+pub fn parse_byte_array(buf: &mut dyn Buf) -> Result<Vec<u8>, MinecraftParseError> {
+    let len = parse_varint_u32(buf)? as usize;
+
+    if buf.remaining() < len {
+        return Err(MinecraftParseError::ArrayTooShort);
+    }
+
+    let mut val = vec![0; len];
+    buf.copy_to_slice(&mut val);
+    Ok(val)
+}
+
+/// Reads a bool and, when it is true, the value that follows it.
+pub fn parse_optional<T, F>(buf: &mut dyn Buf, f: F) -> Result<Option<T>, MinecraftParseError>
+where
+    F: FnOnce(&mut dyn Buf) -> Result<T, MinecraftParseError>,
+{
+    if parse_bool(buf)? {
+        f(buf).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Reads a VarInt count followed by that many elements.
+pub fn parse_array<T, F>(buf: &mut dyn Buf, mut f: F) -> Result<Vec<T>, MinecraftParseError>
+where
+    F: FnMut(&mut dyn Buf) -> Result<T, MinecraftParseError>,
+{
+    let count = parse_varint(buf)?;
+    if !(0..=ARRAY_MAX_LEN).contains(&count) {
+        return Err(MinecraftParseError::ArrayTooLarge(count));
+    }
+
+    let mut val = Vec::new();
+    for _ in 0..count {
+        val.push(f(buf)?);
+    }
+    Ok(val)
+}
+
+// legacy packets prefix UTF-16BE strings with their length in code units
+fn parse_utf16_string(buf: &mut dyn Buf) -> Result<String, MinecraftParseError> {
+    let len = parse_ushort(buf)? as usize;
+    if buf.remaining() < len * 2 {
+        return Err(MinecraftParseError::StringTooShort);
+    }
+
+    let units: Vec<u16> = (0..len).map(|_| buf.get_u16()).collect();
+    String::from_utf16(&units).map_err(|_| MinecraftParseError::InvalidUtf16String)
+}
+
+fn encode_string_n(s: &str, buf: &mut impl BufMut) {
+    VarInt(s.len() as i32).encode(buf);
+    buf.put_slice(s.as_bytes());
+}
+
+/// Reads the leading length prefix of `buf` without consuming it.
+///
+/// Returns `Ok(None)` while the prefix itself is still incomplete, otherwise
+/// `(body_len, header_len)` so the caller knows to wait for
+/// `header_len + body_len` bytes.
+pub fn peek_packet_length(buf: &[u8]) -> Result<Option<(usize, usize)>, MinecraftParseError> {
+    let mut cursor = buf;
+    match parse_varint_counted(&mut cursor) {
+        Ok((len, header_len)) => Ok(Some((len as u32 as usize, header_len))),
+        Err(MinecraftParseError::UnexpectedEof) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub(crate) fn check_packet_len(len: usize, max: usize) -> Result<(), MinecraftParseError> {
+    if len > max {
+        warn!(len, max, "declared packet length over the limit");
+        return Err(MinecraftParseError::PacketTooLarge { len, max });
+    }
+    Ok(())
+}
+
+fn parse_frame<T>(
+    buf: &mut dyn Buf,
+    parse_body: impl FnOnce(i32, &mut dyn Buf) -> Result<T, MinecraftParseError>,
+) -> Result<T, MinecraftParseError> {
+    parse_frame_limited(buf, MAX_PACKET_LEN, parse_body)
+}
+
+fn parse_frame_limited<T>(
+    buf: &mut dyn Buf,
+    max_packet_len: usize,
+    parse_body: impl FnOnce(i32, &mut dyn Buf) -> Result<T, MinecraftParseError>,
+) -> Result<T, MinecraftParseError> {
+    let len = parse_varint_u32(buf)? as usize;
+    trace!(len, "declared packet length");
+    check_packet_len(len, max_packet_len)?;
+    if buf.remaining() < len {
+        return Err(MinecraftParseError::LengthNotMatch);
+    }
+    // only the declared length belongs to this packet; anything after it is
+    // left in `buf` for the next parser
+    let mut body = Buf::take(&mut *buf, len);
+    parse_frame_body(&mut body, parse_body)
+}
+
+fn parse_frame_body<T>(
+    body: &mut dyn Buf,
+    parse_body: impl FnOnce(i32, &mut dyn Buf) -> Result<T, MinecraftParseError>,
+) -> Result<T, MinecraftParseError> {
+    let id = parse_varint(body)?;
+    let val = parse_body(id, body)?;
+
+    if body.has_remaining() {
+        return Err(MinecraftParseError::LengthNotMatch);
+    }
+    Ok(val)
+}
+
+// names the field a packet body ran out in
+fn in_field<T>(label: &'static str, res: Result<T, MinecraftParseError>) -> Result<T, MinecraftParseError> {
+    res.map_err(|e| match e {
+        MinecraftParseError::UnexpectedEof | MinecraftParseError::StringTooShort => {
+            MinecraftParseError::TruncatedField(label)
+        }
+        e => e,
+    })
+}
+
+fn check_handshake_id(id: i32) -> Result<(), MinecraftParseError> {
+    trace!(id, "packet id");
+    if id != 0x00 {
+        warn!(id, "not a handshake packet id");
+        return Err(MinecraftParseError::NotHandshake);
+    }
+    Ok(())
+}
+
+// Where handshake fields are read from. Whole frames and bare bodies both go
+// through `parse_handshake_fields`, so they agree on limits and errors; a
+// source only adds its own context to failures.
+trait FieldSource {
+    fn field<T>(
+        &mut self,
+        label: &'static str,
+        f: impl FnOnce(&mut dyn Buf) -> Result<T, MinecraftParseError>,
+    ) -> Result<T, MinecraftParseError>;
+}
+
+impl<'b> FieldSource for dyn Buf + 'b {
+    fn field<T>(
+        &mut self,
+        label: &'static str,
+        f: impl FnOnce(&mut dyn Buf) -> Result<T, MinecraftParseError>,
+    ) -> Result<T, MinecraftParseError> {
+        in_field(label, f(self))
+    }
+}
+
+fn parse_handshake_fields<S: FieldSource + ?Sized>(src: &mut S) -> Result<Handshake, MinecraftParseError> {
+    trace!(field = "protocol_version");
+    let version = src.field("protocol_version", parse_varint)?;
+    trace!(field = "address");
+    let address = src.field("address", |b| parse_string_n_capped(b, HANDSHAKE_ADDRESS_MAX_BYTES))?;
+    trace!(field = "port");
+    let port = src.field("port", parse_ushort)?;
+    trace!(field = "next_state");
+    let next_state = warn_on_err!(src.field("next_state", |b| NextState::try_from(parse_varint(b)?)), "invalid next state")?;
+
+    let handshake = Handshake {
+        protocol_version: version,
+        address,
+        port,
+        next_state
+    };
+
+    Ok(handshake)
+}
+
+fn parse_handshake_body(id: i32, body: &mut dyn Buf) -> Result<Handshake, MinecraftParseError> {
+    check_handshake_id(id)?;
+    parse_handshake_fields(body)
+}
+
+pub fn parse_handshake(buf: &mut dyn Buf) -> Result<Handshake, MinecraftParseError> {
+    parse_handshake_limited(buf, MAX_PACKET_LEN)
+}
+
+/// Like `parse_handshake`, but rejects a declared length above
+/// `max_packet_len` before looking at the body.
+pub fn parse_handshake_limited(buf: &mut dyn Buf, max_packet_len: usize) -> Result<Handshake, MinecraftParseError> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::trace_span!("parse_handshake", max_packet_len).entered();
+    warn_on_err!(parse_handshake_buf(buf, max_packet_len), "handshake rejected")
+}
+
+// A contiguous buffer is read through `ParseCtx`, with the offsets stripped
+// again so callers see the same errors either way; a chained buffer cannot be
+// viewed as one slice and goes through the frame reader instead.
+fn parse_handshake_buf(buf: &mut dyn Buf, max_packet_len: usize) -> Result<Handshake, MinecraftParseError> {
+    if buf.chunk().len() < buf.remaining() {
+        return parse_frame_limited(buf, max_packet_len, parse_handshake_body);
+    }
+    let mut ctx = ParseCtx::new(buf.chunk());
+    let handshake = parse_handshake_frame(&mut ctx, max_packet_len).map_err(MinecraftParseError::into_inner)?;
+    let consumed = ctx.pos();
+    buf.advance(consumed);
+    Ok(handshake)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseOutcome {
+    Complete { handshake: Handshake, consumed: usize },
+    /// `needed` is how many more bytes the packet requires, or `None` while
+    /// the length prefix itself is incomplete.
+    Incomplete { needed: Option<usize> },
+}
+
+pub fn try_parse_handshake(buf: &[u8]) -> Result<ParseOutcome, MinecraftParseError> {
+    let (body_len, header_len) = match peek_packet_length(buf)? {
+        Some(lens) => lens,
+        None => return Ok(ParseOutcome::Incomplete { needed: None }),
+    };
+    check_packet_len(body_len, MAX_PACKET_LEN)?;
+    let total = header_len.saturating_add(body_len);
+    if buf.len() < total {
+        return Ok(ParseOutcome::Incomplete { needed: Some(total - buf.len()) });
+    }
+
+    let handshake = parse_handshake(&mut &buf[..total])?;
+    Ok(ParseOutcome::Complete { handshake, consumed: total })
+}
+
+/// Entry point for untrusted input: parses a handshake from the front of
+/// `data` and never panics, however the bytes are malformed or truncated.
+pub fn parse_handshake_safe(data: &[u8]) -> Result<Handshake, MinecraftParseError> {
+    let mut cursor = data;
+    parse_handshake(&mut cursor)
+}
+
+/// Parses a handshake from the front of `buf` and returns it together with
+/// the bytes that follow it.
+pub fn parse_handshake_with_rest(buf: &[u8]) -> Result<(Handshake, &[u8]), MinecraftParseError> {
+    let mut rest = buf;
+    let handshake = parse_handshake(&mut rest)?;
+    Ok((handshake, rest))
+}
+
+/// Splits a byte slice into length-framed packets, yielding each packet's
+/// id and body. Iteration stops without an error at a trailing packet that
+/// is still incomplete; `remainder` then returns its bytes.
+#[derive(Debug, Clone)]
+pub struct PacketIter<'a> {
+    buf: &'a [u8],
+    failed: bool,
+}
+
+impl<'a> PacketIter<'a> {
+    pub fn new(buf: &'a [u8]) -> PacketIter<'a> {
+        PacketIter { buf, failed: false }
+    }
+
+    pub fn remainder(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    // a bad length prefix leaves no way to find the next packet
+    fn fail(&mut self, e: MinecraftParseError) -> Result<&'a [u8], MinecraftParseError> {
+        self.failed = true;
+        Err(e)
+    }
+}
+
+impl<'a> Iterator for PacketIter<'a> {
+    type Item = Result<&'a [u8], MinecraftParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let (body_len, header_len) = match peek_packet_length(self.buf) {
+            Ok(Some(lens)) => lens,
+            Ok(None) => return None,
+            Err(e) => return Some(self.fail(e)),
+        };
+        if let Err(e) = check_packet_len(body_len, MAX_PACKET_LEN) {
+            return Some(self.fail(e));
+        }
+        let total = header_len + body_len;
+        if self.buf.len() < total {
+            return None;
+        }
+
+        let body = &self.buf[header_len..total];
+        self.buf = &self.buf[total..];
+        Some(Ok(body))
+    }
+}
+
+/// Parses every complete handshake at the front of `buf`, leaving a partial
+/// trailing packet unconsumed.
+pub fn parse_handshakes(buf: &mut &[u8]) -> Result<Vec<Handshake>, MinecraftParseError> {
+    let mut packets = PacketIter::new(buf);
+    let mut handshakes = Vec::new();
+    for body in &mut packets {
+        handshakes.push(parse_frame_body(&mut &body?[..], parse_handshake_body)?);
+    }
+
+    *buf = packets.remainder();
+    Ok(handshakes)
+}
+
+/// Splits every complete packet at the front of `buf` into its id and body,
+/// leaving a partial trailing packet unconsumed.
+pub fn frame_packets(buf: &mut &[u8]) -> Result<Vec<(i32, Bytes)>, MinecraftParseError> {
+    let mut packets = PacketIter::new(buf);
+    let mut frames = Vec::new();
+    for frame in &mut packets {
+        let mut frame = frame?;
+        let id = parse_varint(&mut frame)?;
+        frames.push((id, Bytes::copy_from_slice(frame)));
+    }
+
+    *buf = packets.remainder();
+    Ok(frames)
+}
+
+#[cfg(feature = "std")]
+fn read_varint<R: Read>(r: &mut R) -> Result<i32, MinecraftParseError> {
+    let mut bytes = [0u8; VARINT_MAX_BYTES];
+    for i in 0..VARINT_MAX_BYTES {
+        r.read_exact(&mut bytes[i..=i])?;
+        if bytes[i] & 0x80 == 0 {
+            return parse_varint(&mut &bytes[..=i]);
+        }
+    }
+    Err(MinecraftParseError::InvalidVarInt)
+}
+
+#[cfg(feature = "std")]
+pub fn parse_handshake_from_reader<R: Read>(r: &mut R) -> Result<Handshake, MinecraftParseError> {
+    let len = read_varint(r)? as u32 as usize;
+    check_packet_len(len, MAX_PACKET_LEN)?;
+
+    // grow the buffer as bytes arrive rather than trusting the declared length
+    let mut body = Vec::new();
+    Read::take(r, len as u64).read_to_end(&mut body)?;
+    if body.len() < len {
+        return Err(MinecraftParseError::UnexpectedEof);
+    }
+
+    parse_frame_body(&mut &body[..], parse_handshake_body)
+}
+
+pub fn parse_handshake_ref<'a>(buf: &'a [u8]) -> Result<HandshakeRef<'a>, MinecraftParseError> {
+    let mut cursor = buf;
+    let len = parse_varint_u32(&mut cursor)? as usize;
+    check_packet_len(len, MAX_PACKET_LEN)?;
+    if cursor.len() < len {
+        return Err(MinecraftParseError::LengthNotMatch);
+    }
+    let mut body = &cursor[..len];
+
+    check_handshake_id(parse_varint(&mut body)?)?;
+
+    // the borrowed address keeps this off `parse_handshake_fields`, so the
+    // fields are labelled here the same way
+    let version = in_field("protocol_version", parse_varint(&mut body))?;
+    let address = in_field("address", parse_str_ref(&mut body, HANDSHAKE_ADDRESS_MAX_BYTES))?;
+    let port = in_field("port", parse_ushort(&mut body))?;
+    let next_state = in_field("next_state", parse_varint(&mut body).and_then(NextState::try_from))?;
+
+    if !body.is_empty() {
+        return Err(MinecraftParseError::LengthNotMatch);
+    }
+
+    let handshake = HandshakeRef {
+        protocol_version: version,
+        address,
+        port,
+        next_state
+    };
+
+    Ok(handshake)
+}
+
+/// Reads fields from a byte slice while tracking the absolute position, so
+/// that errors carry the offset of the field that failed as
+/// `MinecraftParseError::AtOffset`.
+#[derive(Debug, Clone)]
+pub struct ParseCtx<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ParseCtx<'a> {
+    pub fn new(buf: &'a [u8]) -> ParseCtx<'a> {
+        ParseCtx { buf, pos: 0 }
+    }
+
+    /// Offset of the next unread byte from the start of the slice.
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    pub fn varint(&mut self) -> Result<i32, MinecraftParseError> {
+        self.read(parse_varint)
+    }
+
+    pub fn string(&mut self) -> Result<String, MinecraftParseError> {
+        self.read(parse_string_n)
+    }
+
+    pub fn ushort(&mut self) -> Result<u16, MinecraftParseError> {
+        self.read(parse_ushort)
+    }
+
+    fn err_at(&self, offset: usize, e: MinecraftParseError) -> MinecraftParseError {
+        MinecraftParseError::AtOffset { offset, source: Box::new(e) }
+    }
+
+    // on failure the position stays at the start of the field
+    fn read<T>(&mut self, f: impl FnOnce(&mut dyn Buf) -> Result<T, MinecraftParseError>) -> Result<T, MinecraftParseError> {
+        let mut rest = &self.buf[self.pos..];
+        match f(&mut rest) {
+            Ok(v) => {
+                self.pos = self.buf.len() - rest.len();
+                Ok(v)
+            }
+            Err(e) => Err(self.err_at(self.pos, e)),
+        }
+    }
+}
+
+impl FieldSource for ParseCtx<'_> {
+    fn field<T>(
+        &mut self,
+        label: &'static str,
+        f: impl FnOnce(&mut dyn Buf) -> Result<T, MinecraftParseError>,
+    ) -> Result<T, MinecraftParseError> {
+        self.read(|b| in_field(label, f(b)))
+    }
+}
+
+fn parse_handshake_frame(ctx: &mut ParseCtx, max_packet_len: usize) -> Result<Handshake, MinecraftParseError> {
+    let len_pos = ctx.pos();
+    let len = ctx.varint()? as u32 as usize;
+    let start = ctx.pos();
+    check_packet_len(len, max_packet_len).map_err(|e| ctx.err_at(len_pos, e))?;
+    if ctx.remaining() < len {
+        return Err(ctx.err_at(start, MinecraftParseError::LengthNotMatch));
+    }
+    let end = start + len;
+    let mut frame = ParseCtx { buf: &ctx.buf[..end], pos: start };
+
+    let id_pos = frame.pos();
+    let id = frame.varint()?;
+    check_handshake_id(id).map_err(|e| frame.err_at(id_pos, e))?;
+    let handshake = parse_handshake_fields(&mut frame)?;
+    if frame.remaining() != 0 {
+        return Err(frame.err_at(frame.pos(), MinecraftParseError::LengthNotMatch));
+    }
+
+    ctx.pos = end;
+    Ok(handshake)
+}
+
+pub fn parse_status_request(buf: &mut dyn Buf) -> Result<(), MinecraftParseError> {
+    parse_frame(buf, |id, _| {
+        if id != 0x00 {
+            return Err(MinecraftParseError::UnexpectedPacketId(id));
+        }
+        Ok(())
+    })
+}
+
+pub fn parse_ping_request(buf: &mut dyn Buf) -> Result<i64, MinecraftParseError> {
+    parse_frame(buf, |id, body| {
+        if id != 0x01 {
+            return Err(MinecraftParseError::UnexpectedPacketId(id));
+        }
+        parse_long(body)
+    })
+}
+
+/// Parses the client side of a server list ping in one go: a handshake with
+/// next state Status, the Status Request, then the Ping Request, whose
+/// payload is returned. A handshake heading anywhere else fails with
+/// `UnexpectedNextState` before the rest is read.
+pub fn parse_status_flow(buf: &mut dyn Buf) -> Result<(Handshake, i64), MinecraftParseError> {
+    let handshake = parse_handshake(buf)?;
+    if handshake.next_state() != NextState::Status {
+        return Err(MinecraftParseError::UnexpectedNextState(handshake.next_state()));
+    }
+    parse_status_request(buf)?;
+    let payload = parse_ping_request(buf)?;
+    Ok((handshake, payload))
+}
+
+fn parse_login_start_body(body: &mut dyn Buf, protocol_version: i32) -> Result<LoginStart, MinecraftParseError> {
+    if let 759..=760 = protocol_version {
+        return Err(MinecraftParseError::UnsupportedProtocolVersion(protocol_version));
+    }
+    let name = parse_string_max_chars(body, USERNAME_MAX_CHARS)?;
+    let uuid = match protocol_version {
+        v if v >= 764 => Some(parse_uuid(body)?),
+        761..=763 => parse_optional(body, parse_uuid)?,
+        _ => None,
+    };
+
+    Ok(LoginStart { name, uuid })
+}
+
+/// Parses Login Start. The UUID is mandatory from 1.20.2 (protocol 764),
+/// optional behind a bool from 1.19.3 (761) and absent before that; the
+/// 1.19/1.19.1 (759-760) signature data layout is not supported and fails
+/// with `UnsupportedProtocolVersion`.
+pub fn parse_login_start(buf: &mut dyn Buf, protocol_version: i32) -> Result<LoginStart, MinecraftParseError> {
+    parse_frame(buf, |id, body| {
+        if id != 0x00 {
+            return Err(MinecraftParseError::UnexpectedPacketId(id));
+        }
+        parse_login_start_body(body, protocol_version)
+    })
+}
+
+fn parse_property(body: &mut dyn Buf) -> Result<Property, MinecraftParseError> {
+    let name = parse_string_n(body)?;
+    let value = parse_string_n(body)?;
+    let signature = parse_optional(body, parse_string_n)?;
+    Ok(Property { name, value, signature })
+}
+
+/// Parses Login Success. The properties array exists from 1.19 (protocol
+/// 759), and 1.20.5-1.21.1 (766-767) append a strict error handling flag
+/// that is read and dropped. Before 1.16 (735) the UUID was sent as a string,
+/// which is not supported.
+pub fn parse_login_success(buf: &mut dyn Buf, protocol_version: i32) -> Result<LoginSuccess, MinecraftParseError> {
+    if protocol_version < 735 {
+        return Err(MinecraftParseError::UnsupportedProtocolVersion(protocol_version));
+    }
+    parse_frame(buf, |id, body| {
+        if id != 0x02 {
+            return Err(MinecraftParseError::UnexpectedPacketId(id));
+        }
+
+        let uuid = parse_uuid(body)?;
+        let username = parse_string_n(body)?;
+        let properties = if protocol_version >= 759 {
+            parse_array(body, parse_property)?
+        } else {
+            Vec::new()
+        };
+        if let 766..=767 = protocol_version {
+            parse_bool(body)?;
+        }
+
+        Ok(LoginSuccess { uuid, username, properties })
+    })
+}
+
+pub fn parse_encryption_request(buf: &mut dyn Buf, protocol_version: i32) -> Result<EncryptionRequest, MinecraftParseError> {
+    parse_frame(buf, |id, body| {
+        if id != 0x01 {
+            return Err(MinecraftParseError::UnexpectedPacketId(id));
+        }
+
+        let server_id = parse_string_n(body)?;
+        let public_key = parse_byte_array(body)?;
+        let verify_token = parse_byte_array(body)?;
+        let should_authenticate = if protocol_version >= 766 {
+            Some(parse_bool(body)?)
+        } else {
+            None
+        };
+
+        Ok(EncryptionRequest { server_id, public_key, verify_token, should_authenticate })
+    })
+}
+
+/// Parses Encryption Response. Only 1.19-1.19.2 (protocols 759-760) let the
+/// client replace the verify token with a salted signature, flagged by a
+/// bool after the shared secret.
+pub fn parse_encryption_response(buf: &mut dyn Buf, protocol_version: i32) -> Result<EncryptionResponse, MinecraftParseError> {
+    parse_frame(buf, |id, body| {
+        if id != 0x01 {
+            return Err(MinecraftParseError::UnexpectedPacketId(id));
+        }
+
+        let shared_secret = parse_byte_array(body)?;
+        let has_verify_token = match protocol_version {
+            759..=760 => parse_bool(body)?,
+            _ => true,
+        };
+        let (verify_token, salt_signature) = if has_verify_token {
+            (Some(parse_byte_array(body)?), None)
+        } else {
+            let salt = parse_long(body)?;
+            (None, Some((salt, parse_byte_array(body)?)))
+        };
+
+        Ok(EncryptionResponse { shared_secret, verify_token, salt_signature })
+    })
+}
+
+/// Returns the Set Compression threshold: packets of at least this many bytes
+/// are compressed from now on, and a negative value disables compression.
+pub fn parse_set_compression(buf: &mut dyn Buf) -> Result<i32, MinecraftParseError> {
+    parse_frame(buf, |id, body| {
+        if id != 0x03 {
+            return Err(MinecraftParseError::UnexpectedPacketId(id));
+        }
+        parse_varint(body)
+    })
+}
+
+/// Returns the raw JSON text component explaining why the server refused the
+/// login; feed it to `parse_text_component` for a readable message.
+pub fn parse_login_disconnect(buf: &mut dyn Buf) -> Result<String, MinecraftParseError> {
+    parse_frame(buf, |id, body| {
+        if id != 0x00 {
+            return Err(MinecraftParseError::UnexpectedPacketId(id));
+        }
+        parse_string_n(body)
+    })
+}
+
+/// Parses the body of a Keep Alive packet, after its id. The id differs by
+/// direction and state, so the caller strips it. The body must be exactly
+/// the 8-byte long used since 1.12.2 (protocol 340).
+pub fn parse_keep_alive(buf: &mut dyn Buf) -> Result<i64, MinecraftParseError> {
+    let id = parse_long(buf)?;
+    if buf.has_remaining() {
+        return Err(MinecraftParseError::LengthNotMatch);
+    }
+    Ok(id)
+}
+
+/// Parses the body of a Plugin Message, after its id. The data has no length
+/// prefix and runs to the end of `buf`, so `buf` must hold exactly one packet
+/// body, e.g. the one handed to a `parse_frame` closure.
+pub fn parse_plugin_message(buf: &mut dyn Buf) -> Result<PluginMessage, MinecraftParseError> {
+    let channel = parse_identifier(buf)?;
+    let data = buf.copy_to_bytes(buf.remaining()).to_vec();
+    Ok(PluginMessage { channel, data })
+}
+
+/// Parses the body of Client Information, after its id. The last field
+/// arrived in 1.18 (protocol 757), so older layouts are not supported, and
+/// the particle status added in 1.21.2 (768) is read and dropped.
+pub fn parse_client_information(buf: &mut dyn Buf, protocol_version: i32) -> Result<ClientInformation, MinecraftParseError> {
+    if protocol_version < 757 {
+        return Err(MinecraftParseError::UnsupportedProtocolVersion(protocol_version));
+    }
+    let locale = parse_string_max_chars(buf, 16)?;
+    let view_distance = parse_byte(buf)?;
+    let chat_mode = parse_varint(buf)?;
+    let chat_colors = parse_bool(buf)?;
+    let skin_parts = parse_ubyte(buf)?;
+    let main_hand = parse_varint(buf)?;
+    let text_filtering = parse_bool(buf)?;
+    let allow_listing = parse_bool(buf)?;
+    if protocol_version >= 768 {
+        parse_varint(buf)?;
+    }
+    Ok(ClientInformation {
+        locale,
+        view_distance,
+        chat_mode,
+        chat_colors,
+        skin_parts,
+        main_hand,
+        text_filtering,
+        allow_listing,
+    })
+}
+
+/// Parses the body of Set Player Position, after its id. The on-ground flag
+/// is a strict bool, as sent before 1.21.2 (protocol 768) turned it into a
+/// bit field.
+pub fn parse_player_position(buf: &mut dyn Buf) -> Result<PlayerPosition, MinecraftParseError> {
+    let x = parse_double(buf)?;
+    let y = parse_double(buf)?;
+    let z = parse_double(buf)?;
+    let on_ground = parse_bool(buf)?;
+    Ok(PlayerPosition { x, y, z, on_ground })
+}
+
+/// Parses the body of Set Player Position and Rotation, after its id, with
+/// the same on-ground handling as [`parse_player_position`].
+pub fn parse_player_position_rotation(buf: &mut dyn Buf) -> Result<PlayerPositionRotation, MinecraftParseError> {
+    let x = parse_double(buf)?;
+    let y = parse_double(buf)?;
+    let z = parse_double(buf)?;
+    let yaw = parse_float(buf)?;
+    let pitch = parse_float(buf)?;
+    let on_ground = parse_bool(buf)?;
+    Ok(PlayerPositionRotation { x, y, z, yaw, pitch, on_ground })
+}
+
+/// Parses an item stack in the 1.13.2-1.20.4 (protocol 404-765) layout: a
+/// present flag, then the item id, count and NBT. The NBT root is named
+/// before 1.20.2 (764), and a lone end tag means the stack has none. The
+/// data component layout of 1.20.5+ is not supported.
+pub fn parse_slot(buf: &mut dyn Buf, protocol_version: i32) -> Result<Slot, MinecraftParseError> {
+    if !(404..766).contains(&protocol_version) {
+        return Err(MinecraftParseError::UnsupportedProtocolVersion(protocol_version));
+    }
+    if !parse_bool(buf)? {
+        return Ok(Slot { item_id: None, count: 0, nbt: None });
+    }
+    let item_id = parse_varint(buf)?;
+    let count = parse_byte(buf)?;
+    let nbt = if protocol_version >= 764 {
+        parse_nbt_network(buf)?
+    } else {
+        parse_nbt_named(buf)?.1
+    };
+    let nbt = match nbt {
+        NbtTag::End => None,
+        tag => Some(tag),
+    };
+    Ok(Slot { item_id: Some(item_id), count, nbt })
+}
+
+/// Parses the body of Select Known Packs, after its id. Sent in both
+/// directions of the configuration state since 1.20.5 (protocol 766).
+pub fn parse_known_packs(buf: &mut dyn Buf) -> Result<Vec<KnownPack>, MinecraftParseError> {
+    parse_array(buf, |b| {
+        let namespace = parse_string_n(b)?;
+        let id = parse_string_n(b)?;
+        let version = parse_string_n(b)?;
+        Ok(KnownPack { namespace, id, version })
+    })
+}
+
+/// Parses the body of a Cookie Request, after its id. Cookies exist since
+/// 1.20.5 (protocol 766) in the login, configuration and play states, each
+/// with its own packet id.
+pub fn parse_cookie_request(buf: &mut dyn Buf) -> Result<CookieRequest, MinecraftParseError> {
+    let key = parse_identifier(buf)?;
+    Ok(CookieRequest { key })
+}
+
+/// Parses the body of a Cookie Response, after its id. A payload declared
+/// longer than 5120 bytes is rejected before it is read.
+pub fn parse_cookie_response(buf: &mut dyn Buf) -> Result<CookieResponse, MinecraftParseError> {
+    let key = parse_identifier(buf)?;
+    let payload = parse_optional(buf, |b| {
+        let len = parse_varint_u32(b)? as usize;
+        if len > COOKIE_MAX_BYTES {
+            return Err(MinecraftParseError::CookieTooLarge(len));
+        }
+        if b.remaining() < len {
+            return Err(MinecraftParseError::ArrayTooShort);
+        }
+        Ok(b.copy_to_bytes(len).to_vec())
+    })?;
+    Ok(CookieResponse { key, payload })
+}
+
+fn parse_chat_signature(body: &mut dyn Buf) -> Result<Vec<u8>, MinecraftParseError> {
+    // fixed-size since 1.19.3
+    if body.remaining() < 256 {
+        return Err(MinecraftParseError::UnexpectedEof);
+    }
+    let mut val = vec![0; 256];
+    body.copy_to_slice(&mut val);
+    Ok(val)
+}
+
+/// Parses the body of the serverbound Chat Message play packet, after its
+/// id. Supports the signed chat layouts from 1.19 (protocol 759) on: 1.19 and
+/// 1.19.1-1.19.2 send a byte array signature, possibly empty, and newer
+/// versions an optional 256-byte one. Message acknowledgement fields are
+/// read and dropped.
+pub fn parse_chat_message(buf: &mut dyn Buf, protocol_version: i32) -> Result<ChatMessage, MinecraftParseError> {
+    if protocol_version < 759 {
+        return Err(MinecraftParseError::UnsupportedProtocolVersion(protocol_version));
+    }
+
+    let message = parse_string_n(buf)?;
+    let timestamp = parse_long(buf)?;
+    let salt = parse_long(buf)?;
+    let signature = if protocol_version <= 760 {
+        let signature = parse_byte_array(buf)?;
+        // signed preview
+        parse_bool(buf)?;
+        if protocol_version == 760 {
+            // last seen messages, then the last received one
+            parse_array(buf, |b| {
+                parse_uuid(b)?;
+                parse_byte_array(b)
+            })?;
+            parse_optional(buf, |b| {
+                parse_uuid(b)?;
+                parse_byte_array(b)
+            })?;
+        }
+        Some(signature).filter(|s| !s.is_empty())
+    } else {
+        let signature = parse_optional(buf, parse_chat_signature)?;
+        // message count and the 20-bit acknowledged bitset
+        parse_varint(buf)?;
+        if buf.remaining() < 3 {
+            return Err(MinecraftParseError::UnexpectedEof);
+        }
+        buf.advance(3);
+        if protocol_version >= 770 {
+            // checksum
+            if buf.remaining() < 1 {
+                return Err(MinecraftParseError::UnexpectedEof);
+            }
+            buf.advance(1);
+        }
+        signature
+    };
+
+    Ok(ChatMessage { message, timestamp, salt, signature })
+}
+
+/// Parses Login Plugin Request. Its data runs to the end of the frame.
+pub fn parse_login_plugin_request(buf: &mut dyn Buf) -> Result<LoginPluginRequest, MinecraftParseError> {
+    parse_frame(buf, |id, body| {
+        if id != 0x04 {
+            return Err(MinecraftParseError::UnexpectedPacketId(id));
+        }
+
+        let message_id = parse_varint(body)?;
+        let channel = parse_identifier(body)?;
+        let data = body.copy_to_bytes(body.remaining()).to_vec();
+        Ok(LoginPluginRequest { message_id, channel, data })
+    })
+}
+
+/// Parses Login Plugin Response. Data, running to the end of the frame, is
+/// only allowed when the successful flag is set.
+pub fn parse_login_plugin_response(buf: &mut dyn Buf) -> Result<LoginPluginResponse, MinecraftParseError> {
+    parse_frame(buf, |id, body| {
+        if id != 0x02 {
+            return Err(MinecraftParseError::UnexpectedPacketId(id));
+        }
+
+        let message_id = parse_varint(body)?;
+        // an unsuccessful response with trailing bytes fails the frame's
+        // length check
+        let data = parse_optional(body, |b| Ok(b.copy_to_bytes(b.remaining()).to_vec()))?;
+        Ok(LoginPluginResponse { message_id, data })
+    })
+}
+
+/// The kind of client connection, as told apart by its first bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolFlavor {
+    /// A VarInt-framed handshake, used from 1.7 on.
+    Modern,
+    /// The 1.6 ping, `0xFE 0x01 0xFA`; see [`parse_legacy_ping`].
+    Legacy16,
+    /// A ping from 1.5 or older: `0xFE 0x01`, or a lone `0xFE` before 1.4.
+    Legacy14,
+}
+
+/// Tells which parser the first bytes of a connection belong to, or `None`
+/// while the bytes buffered so far could still begin more than one. A leading
+/// `0xFE` always means a legacy ping, as it does to vanilla. Clients before
+/// 1.6 send only `0xFE` or `0xFE 0x01` and then wait, so a caller that times
+/// out on either of those should treat the ping as `Legacy14`.
+pub fn detect_protocol(buf: &[u8]) -> Option<ProtocolFlavor> {
+    match buf {
+        [] | [0xFE] | [0xFE, 0x01] => None,
+        [0xFE, 0x01, 0xFA, ..] => Some(ProtocolFlavor::Legacy16),
+        [0xFE, ..] => Some(ProtocolFlavor::Legacy14),
+        _ => Some(ProtocolFlavor::Modern),
+    }
+}
+
+/// Parses the 1.6 legacy server list ping: `0xFE 0x01` followed by an
+/// `MC|PingHost` plugin message carrying the protocol, hostname and port.
+pub fn parse_legacy_ping(buf: &mut dyn Buf) -> Result<LegacyPing, MinecraftParseError> {
+    if buf.remaining() < 3 {
+        return Err(MinecraftParseError::UnexpectedEof);
+    }
+    if buf.get_u8() != 0xFE || buf.get_u8() != 0x01 || buf.get_u8() != 0xFA {
+        return Err(MinecraftParseError::NotLegacyPing);
+    }
+    if parse_utf16_string(buf)? != "MC|PingHost" {
+        return Err(MinecraftParseError::NotLegacyPing);
+    }
+
+    let len = parse_ushort(buf)? as usize;
+    if buf.remaining() < len {
+        return Err(MinecraftParseError::LengthNotMatch);
+    }
+    let mut data = Buf::take(&mut *buf, len);
+
+    if data.remaining() < 1 {
+        return Err(MinecraftParseError::UnexpectedEof);
+    }
+    let protocol_version = data.get_u8();
+    let hostname = parse_utf16_string(&mut data)?;
+    let port = parse_int(&mut data)?;
+
+    if data.has_remaining() {
+        return Err(MinecraftParseError::LengthNotMatch);
+    }
+
+    Ok(LegacyPing { protocol_version, hostname, port })
+}
+
+/// Serverbound packets understood by `parse_packet`, keyed by the state the
+/// connection is in when they arrive.
+#[derive(Debug, PartialEq)]
+pub enum Packet {
+    Handshake(Handshake),
+    StatusRequest,
+    PingRequest(i64),
+    LoginStart(LoginStart),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Handshaking,
+    Status,
+    /// Login Start's layout depends on the version announced in the handshake.
+    Login { protocol_version: i32 },
+}
+
+pub fn parse_packet(state: ConnectionState, buf: &mut dyn Buf) -> Result<Packet, MinecraftParseError> {
+    parse_frame(buf, |id, body| match (state, id) {
+        (ConnectionState::Handshaking, _) => parse_handshake_body(id, body).map(Packet::Handshake),
+        (ConnectionState::Status, 0x00) => Ok(Packet::StatusRequest),
+        (ConnectionState::Status, 0x01) => parse_long(body).map(Packet::PingRequest),
+        (ConnectionState::Login { protocol_version }, 0x00) => {
+            parse_login_start_body(body, protocol_version).map(Packet::LoginStart)
+        }
+        (_, id) => Err(MinecraftParseError::UnexpectedPacketId(id)),
+    })
+}
+
+pub fn encode_handshake(handshake: &Handshake, buf: &mut impl BufMut) {
+    let mut body = Vec::new();
+    VarInt(0x00).encode(&mut body);
+    VarInt(handshake.protocol_version).encode(&mut body);
+    encode_string_n(&handshake.address, &mut body);
+    body.put_u16(handshake.port);
+    VarInt(handshake.next_state.into()).encode(&mut body);
+
+    VarInt(body.len() as i32).encode(buf);
+    buf.put_slice(&body);
+}
+
+/// Composes an arbitrary packet field by field; `build` prepends the length
+/// of the packet id and fields together.
+#[derive(Debug, Clone)]
+pub struct PacketBuilder {
+    body: Vec<u8>,
+}
+
+impl PacketBuilder {
+    pub fn new(packet_id: i32) -> PacketBuilder {
+        let mut body = Vec::new();
+        VarInt(packet_id).encode(&mut body);
+        PacketBuilder { body }
+    }
+
+    pub fn varint(mut self, value: i32) -> PacketBuilder {
+        VarInt(value).encode(&mut self.body);
+        self
+    }
+
+    pub fn string(mut self, s: &str) -> PacketBuilder {
+        encode_string_n(s, &mut self.body);
+        self
+    }
+
+    pub fn ushort(mut self, value: u16) -> PacketBuilder {
+        self.body.put_u16(value);
+        self
+    }
+
+    /// Appends `bytes` as they are, with no length prefix.
+    pub fn bytes(mut self, bytes: &[u8]) -> PacketBuilder {
+        self.body.put_slice(bytes);
+        self
+    }
+
+    pub fn build(self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(varint_len(self.body.len() as i32) + self.body.len());
+        VarInt(self.body.len() as i32).encode(&mut out);
+        out.extend(self.body);
+        out
+    }
+}
+
+impl Decodable for Handshake {
+    fn decode(buf: &mut dyn Buf) -> Result<Self, MinecraftParseError> {
+        parse_handshake(buf)
+    }
+}
+
+impl Encodable for Handshake {
+    fn encode(&self, mut buf: &mut dyn BufMut) {
+        encode_handshake(self, &mut buf);
+    }
+}
+
+/// Parses the handshake at the front of the slice with
+/// [`parse_handshake_safe`]; bytes after it are ignored.
+impl<'a> TryFrom<&'a [u8]> for Handshake {
+    type Error = MinecraftParseError;
+
+    fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+        parse_handshake_safe(data)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    mod parse_varint {
+        use crate::*;
+
+        #[test]
+        fn parse_1_byte_positive_value() {
+            let mut buf = &b"\x16"[..];
+            let val = parse_varint(&mut buf).unwrap();
+            assert_eq!(val, 22);
+        }
+        #[test]
+        fn parse_0() {
+            let mut buf = &b"\x00"[..];
+            let val = parse_varint(&mut buf).unwrap();
+            assert_eq!(val, 0);
+        }
+
+        #[test]
+        fn parse_754() {
+            let mut buf = &b"\xf2\x05"[..];
+            let val = parse_varint(&mut buf).unwrap();
+            assert_eq!(val, 754);
+        }
+
+        #[test]
+        fn reject_empty_packet() {
+            let mut buf = &b""[..];
+            let val = parse_handshake(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+
+        #[test]
+        fn reject_not_terminated_packet() {
+            let mut buf = &b"\xd7"[..];
+            let val = parse_handshake(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+
+        #[test]
+        fn reject_dangling_continuation_bit() {
+            let mut buf = &b"\x80"[..];
+            let val = parse_varint(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+        #[test]
+        fn parse_max_5_byte_value() {
+            let mut buf = &b"\xff\xff\xff\xff\x0f"[..];
+            let val = parse_varint(&mut buf).unwrap();
+            assert_eq!(val, -1);
+        }
+
+        #[test]
+        fn reject_bit_32() {
+            // 1 << 32: only the bit just past an i32 is set
+            let mut buf = &b"\x80\x80\x80\x80\x10"[..];
+            let val = parse_varint(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::VarIntMalformed));
+        }
+
+        #[test]
+        fn accept_top_bits_of_5th_byte() {
+            let mut buf = &b"\x80\x80\x80\x80\x08"[..];
+            let val = parse_varint(&mut buf).unwrap();
+            assert_eq!(val, i32::MIN);
+        }
+
+        #[test]
+        fn reject_overflowing_5th_byte() {
+            let mut buf = &b"\xff\xff\xff\xff\x7f"[..];
+            let val = parse_varint(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::VarIntMalformed));
+        }
+
+        #[test]
+        fn reject_too_long_num() {
+            let mut buf = &b"\xf3\xf3\xf3\xf3\xf3\x05"[..];
+            let val = parse_varint(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::InvalidVarInt));
+        }
+    }
+
+    mod parse_varint_u32 {
+        use crate::*;
+
+        #[test]
+        fn parse_max_as_unsigned() {
+            let mut buf = &b"\xff\xff\xff\xff\x0f"[..];
+            let val = parse_varint_u32(&mut buf).unwrap();
+            assert_eq!(val, 4294967295);
+            assert_eq!(val as usize, u32::MAX as usize);
+        }
+
+        #[test]
+        fn parse_small_value() {
+            let mut buf = &b"\xac\x02"[..];
+            assert_eq!(parse_varint_u32(&mut buf).unwrap(), 300);
+        }
+
+        #[test]
+        fn negative_length_reported_as_unsigned() {
+            let mut buf = &b"\xff\xff\xff\xff\x0f\x00"[..];
+            let val = parse_handshake(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::PacketTooLarge { len: 4294967295, .. }));
+        }
+    }
+
+    mod parse_varint_strict {
+        use crate::*;
+
+        #[test]
+        fn accept_canonical() {
+            assert_eq!(parse_varint_strict(&mut &b"\x00"[..]).unwrap(), 0);
+            assert_eq!(parse_varint_strict(&mut &b"\xf2\x05"[..]).unwrap(), 754);
+            assert_eq!(parse_varint_strict(&mut &b"\xff\xff\xff\xff\x0f"[..]).unwrap(), -1);
+        }
+
+        #[test]
+        fn reject_overlong_zero() {
+            let val = parse_varint_strict(&mut &b"\x80\x00"[..]).err().unwrap();
+            assert_eq!(val, MinecraftParseError::VarIntNonCanonical);
+            // the lenient parser still accepts it
+            assert_eq!(parse_varint(&mut &b"\x80\x00"[..]).unwrap(), 0);
+        }
+
+        #[test]
+        fn reject_padded_value() {
+            let val = parse_varint_strict(&mut &b"\xf2\x85\x80\x00"[..]).err().unwrap();
+            assert_eq!(val, MinecraftParseError::VarIntNonCanonical);
+        }
+    }
+
+    mod decode_varint_iter {
+        use crate::*;
+
+        #[test]
+        fn decode_from_vec() {
+            let mut iter = vec![0xf2, 0x05].into_iter();
+            assert_eq!(decode_varint_iter(&mut iter).unwrap(), 754);
+        }
+
+        #[test]
+        fn leave_following_bytes() {
+            let mut iter = vec![0x7f, 0x01].into_iter();
+            assert_eq!(decode_varint_iter(&mut iter).unwrap(), 127);
+            assert_eq!(iter.next(), Some(0x01));
+        }
+
+        #[test]
+        fn reject_ended_iterator() {
+            let mut iter = vec![0xf2].into_iter();
+            let val = decode_varint_iter(&mut iter).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+
+        #[test]
+        fn reject_too_long() {
+            let mut iter = vec![0xff, 0xff, 0xff, 0xff, 0xff, 0x01].into_iter();
+            let val = decode_varint_iter(&mut iter).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::InvalidVarInt));
+        }
+
+        #[test]
+        fn reject_overflow() {
+            let mut iter = vec![0xff, 0xff, 0xff, 0xff, 0x1f].into_iter();
+            let val = decode_varint_iter(&mut iter).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::VarIntMalformed));
+        }
+    }
+
+    mod parse_varint_counted {
+        use crate::*;
+
+        #[test]
+        fn count_2_bytes() {
+            let mut buf = &b"\xf2\x05"[..];
+            let val = parse_varint_counted(&mut buf).unwrap();
+            assert_eq!(val, (754, 2));
+        }
+
+        #[test]
+        fn count_1_byte() {
+            let mut buf = &b"\x00"[..];
+            let val = parse_varint_counted(&mut buf).unwrap();
+            assert_eq!(val, (0, 1));
+        }
+
+        #[test]
+        fn count_only_varint_bytes() {
+            let mut buf = &b"\xff\xff\xff\xff\x0f\x01"[..];
+            let val = parse_varint_counted(&mut buf).unwrap();
+            assert_eq!(val, (-1, 5));
+            assert_eq!(buf, b"\x01");
+        }
+    }
+
+    mod varint {
+        use crate::*;
+
+        fn round_trip(value: i32) -> i32 {
+            let mut out = Vec::new();
+            VarInt(value).encode(&mut out);
+            assert_eq!(out.len(), VarInt(value).encoded_len());
+            let mut buf = &out[..];
+            let decoded = VarInt::decode(&mut buf).unwrap();
+            assert_eq!(buf.remaining(), 0);
+            decoded.0
+        }
+
+        #[test]
+        fn encode_754() {
+            let mut out = Vec::new();
+            VarInt(754).encode(&mut out);
+            assert_eq!(out, b"\xf2\x05");
+        }
+
+        #[test]
+        fn encode_negative_as_5_bytes() {
+            let mut out = Vec::new();
+            VarInt(-1).encode(&mut out);
+            assert_eq!(out, b"\xff\xff\xff\xff\x0f");
+        }
+
+        #[test]
+        fn round_trip_edge_values() {
+            for &value in &[0, 1, 127, 128, 754, 25565, 2097151, i32::MAX, -1, i32::MIN] {
+                assert_eq!(round_trip(value), value);
+            }
+        }
+
+        #[test]
+        fn encoded_len_by_magnitude() {
+            assert_eq!(VarInt(0).encoded_len(), 1);
+            assert_eq!(VarInt(127).encoded_len(), 1);
+            assert_eq!(VarInt(128).encoded_len(), 2);
+            assert_eq!(VarInt(2097151).encoded_len(), 3);
+            assert_eq!(VarInt(i32::MAX).encoded_len(), 5);
+            assert_eq!(VarInt(i32::MIN).encoded_len(), 5);
+        }
+    }
+
+    mod write_varint_array {
+        use crate::*;
+
+        #[test]
+        fn write_2_bytes() {
+            let (bytes, len) = write_varint_array(754);
+            assert_eq!(len, 2);
+            assert_eq!(&bytes[..len], b"\xf2\x05");
+        }
+
+        #[test]
+        fn write_negative_in_5_bytes() {
+            assert_eq!(write_varint_array(-1), ([0xff, 0xff, 0xff, 0xff, 0x0f], 5));
+        }
+
+        #[test]
+        fn write_zero() {
+            assert_eq!(write_varint_array(0), ([0; 5], 1));
+        }
+
+        #[test]
+        fn agree_with_varint_len() {
+            for value in [1, 127, 128, 16383, 16384, 2097151, 2097152, i32::MAX, i32::MIN] {
+                let (bytes, len) = write_varint_array(value);
+                assert_eq!(len, varint_len(value));
+                assert_eq!(parse_varint(&mut &bytes[..len]).unwrap(), value);
+            }
+        }
+    }
+
+    mod varint_len {
+        use crate::*;
+
+        #[test]
+        fn len_of_0() {
+            assert_eq!(varint_len(0), 1);
+        }
+
+        #[test]
+        fn len_of_754() {
+            assert_eq!(varint_len(754), 2);
+        }
+
+        #[test]
+        fn len_of_minus_1() {
+            assert_eq!(varint_len(-1), 5);
+        }
+    }
+
+    mod varint_proptest {
+        use crate::*;
+        use proptest::prelude::*;
+
+        proptest! {
+            #[test]
+            fn round_trip(value in any::<i32>()) {
+                let mut out = Vec::new();
+                VarInt(value).encode(&mut out);
+                let mut buf = &out[..];
+                prop_assert_eq!(VarInt::decode(&mut buf).unwrap(), VarInt(value));
+                prop_assert!(buf.is_empty());
+            }
+
+            #[test]
+            fn encoded_len_is_1_to_5_bytes(value in any::<i32>()) {
+                let mut out = Vec::new();
+                VarInt(value).encode(&mut out);
+                prop_assert!((1..=VARINT_MAX_BYTES).contains(&out.len()));
+                prop_assert_eq!(out.len(), VarInt(value).encoded_len());
+            }
+
+            #[test]
+            fn arbitrary_bytes_never_panic(bytes in proptest::collection::vec(any::<u8>(), 0..16)) {
+                let mut buf = &bytes[..];
+                let _ = parse_varint(&mut buf);
+            }
+        }
+    }
+
+    mod parse_varlong {
+        use crate::*;
+
+        #[test]
+        fn parse_1() {
+            let mut buf = &b"\x01"[..];
+            let val = parse_varlong(&mut buf).unwrap();
+            assert_eq!(val, 1);
+        }
+
+        #[test]
+        fn parse_minus_1() {
+            let mut buf = &b"\xff\xff\xff\xff\xff\xff\xff\xff\xff\x01"[..];
+            let val = parse_varlong(&mut buf).unwrap();
+            assert_eq!(val, -1);
+        }
+
+        #[test]
+        fn parse_2147483648() {
+            let mut buf = &b"\x80\x80\x80\x80\x08"[..];
+            let val = parse_varlong(&mut buf).unwrap();
+            assert_eq!(val, 2147483648);
+        }
+
+        #[test]
+        fn reject_bit_64() {
+            let mut buf = &b"\x80\x80\x80\x80\x80\x80\x80\x80\x80\x02"[..];
+            let val = parse_varlong(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::VarLongMalformed));
+        }
+
+        #[test]
+        fn reject_overflowing_10th_byte() {
+            let mut buf = &b"\xff\xff\xff\xff\xff\xff\xff\xff\xff\x02"[..];
+            let val = parse_varlong(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::VarLongMalformed));
+        }
+
+        #[test]
+        fn reject_too_long_num() {
+            let mut buf = &b"\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\x01"[..];
+            let val = parse_varlong(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::InvalidVarLong));
+        }
+
+        #[test]
+        fn reject_not_terminated() {
+            let mut buf = &b"\x80\x80"[..];
+            let val = parse_varlong(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+
+        #[test]
+        fn round_trip_edge_values() {
+            for &value in &[0, 1, -1, 2147483648, i64::MAX, i64::MIN] {
+                let mut out = Vec::new();
+                VarLong(value).encode(&mut out);
+                let mut buf = &out[..];
+                assert_eq!(VarLong::decode(&mut buf).unwrap(), VarLong(value));
+            }
+        }
+    }
+
+    mod parse_ushort {
+        use crate::*;
+
+        #[test]
+        fn parse_25565() {
+            let mut buf = &b"\x63\xdd"[..];
+            let val = parse_ushort(&mut buf).unwrap();
+            assert_eq!(val, 25565);
+        }
+
+        #[test]
+        fn reject_1_byte() {
+            let mut buf = &b"\x63"[..];
+            let val = parse_ushort(&mut buf).err().unwrap();
+            assert_eq!(val, MinecraftParseError::UnexpectedEof);
+        }
+
+        #[test]
+        fn reject_empty() {
+            let mut buf = &b""[..];
+            let val = parse_ushort(&mut buf).err().unwrap();
+            assert_eq!(val, MinecraftParseError::UnexpectedEof);
+        }
+    }
+
+    mod parse_short {
+        use crate::*;
+
+        #[test]
+        fn parse_negative() {
+            let mut buf = &b"\xff\xfe"[..];
+            let val = parse_short(&mut buf).unwrap();
+            assert_eq!(val, -2);
+        }
+
+        #[test]
+        fn reject_1_byte() {
+            let mut buf = &b"\x63"[..];
+            let val = parse_short(&mut buf).err().unwrap();
+            assert_eq!(val, MinecraftParseError::UnexpectedEof);
+        }
+    }
+
+    mod parse_byte {
+        use crate::*;
+
+        #[test]
+        fn parse_sign() {
+            let mut buf = &b"\xff\x7f\x80"[..];
+            assert_eq!(parse_byte(&mut buf).unwrap(), -1);
+            assert_eq!(parse_byte(&mut buf).unwrap(), 127);
+            assert_eq!(parse_byte(&mut buf).unwrap(), -128);
+        }
+
+        #[test]
+        fn reject_empty() {
+            let mut buf = &b""[..];
+            let val = parse_byte(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+    }
+
+    mod parse_ubyte {
+        use crate::*;
+
+        #[test]
+        fn parse_unsigned() {
+            let mut buf = &b"\xff\x00"[..];
+            assert_eq!(parse_ubyte(&mut buf).unwrap(), 255);
+            assert_eq!(parse_ubyte(&mut buf).unwrap(), 0);
+        }
+
+        #[test]
+        fn reject_empty() {
+            let mut buf = &b""[..];
+            let val = parse_ubyte(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+    }
+
+    mod parse_int {
+        use crate::*;
+
+        #[test]
+        fn parse_25565() {
+            let mut buf = &b"\x00\x00\x63\xdd"[..];
+            let val = parse_int(&mut buf).unwrap();
+            assert_eq!(val, 25565);
+        }
+
+        #[test]
+        fn parse_negative() {
+            let mut buf = &b"\xff\xff\xff\xff"[..];
+            let val = parse_int(&mut buf).unwrap();
+            assert_eq!(val, -1);
+        }
+
+        #[test]
+        fn reject_3_bytes() {
+            let mut buf = &b"\x00\x63\xdd"[..];
+            let val = parse_int(&mut buf).err().unwrap();
+            assert_eq!(val, MinecraftParseError::UnexpectedEof);
+        }
+    }
+
+    mod parse_long {
+        use crate::*;
+
+        #[test]
+        fn parse_42() {
+            let mut buf = &b"\x00\x00\x00\x00\x00\x00\x00\x2a"[..];
+            let val = parse_long(&mut buf).unwrap();
+            assert_eq!(val, 42);
+        }
+
+        #[test]
+        fn parse_min() {
+            let mut buf = &b"\x80\x00\x00\x00\x00\x00\x00\x00"[..];
+            let val = parse_long(&mut buf).unwrap();
+            assert_eq!(val, i64::MIN);
+        }
+
+        #[test]
+        fn reject_7_bytes() {
+            let mut buf = &b"\x00\x00\x00\x00\x00\x00\x2a"[..];
+            let val = parse_long(&mut buf).err().unwrap();
+            assert_eq!(val, MinecraftParseError::UnexpectedEof);
+        }
+    }
+
+    mod parse_float {
+        use crate::*;
+
+        #[test]
+        fn parse_health() {
+            let mut buf = &b"\x41\xa0\x00\x00"[..];
+            let val = parse_float(&mut buf).unwrap();
+            assert_eq!(val, 20.0);
+        }
+
+        #[test]
+        fn parse_negative() {
+            let mut buf = &b"\xbf\x00\x00\x00"[..];
+            let val = parse_float(&mut buf).unwrap();
+            assert_eq!(val, -0.5);
+        }
+
+        #[test]
+        fn reject_3_bytes() {
+            let mut buf = &b"\x41\xa0\x00"[..];
+            let val = parse_float(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+    }
+
+    mod parse_double {
+        use crate::*;
+
+        #[test]
+        fn parse_coordinate() {
+            let mut buf = &b"\xc0\x5e\xdd\x2f\x1a\x9f\xbe\x77"[..];
+            let val = parse_double(&mut buf).unwrap();
+            assert_eq!(val, -123.456);
+        }
+
+        #[test]
+        fn parse_nan() {
+            let mut buf = &b"\x7f\xf8\x00\x00\x00\x00\x00\x00"[..];
+            let val = parse_double(&mut buf).unwrap();
+            assert!(val.is_nan());
+        }
+
+        #[test]
+        fn reject_7_bytes() {
+            let mut buf = &b"\xc0\x5e\xdd\x2f\x1a\x9f\xbe"[..];
+            let val = parse_double(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+    }
+
+    mod parse_bool {
+        use crate::*;
+
+        #[test]
+        fn parse_false() {
+            let mut buf = &b"\x00"[..];
+            assert!(!parse_bool(&mut buf).unwrap());
+        }
+
+        #[test]
+        fn parse_true() {
+            let mut buf = &b"\x01"[..];
+            assert!(parse_bool(&mut buf).unwrap());
+        }
+
+        #[test]
+        fn reject_other_byte() {
+            let mut buf = &b"\x02"[..];
+            let val = parse_bool(&mut buf).err().unwrap();
+            assert_eq!(val, MinecraftParseError::InvalidBool(0x02));
+        }
+
+        #[test]
+        fn reject_empty() {
+            let mut buf = &b""[..];
+            let val = parse_bool(&mut buf).err().unwrap();
+            assert_eq!(val, MinecraftParseError::UnexpectedEof);
+        }
+    }
+
+    mod parse_uuid {
+        use crate::*;
+
+        #[test]
+        fn parse_and_format() {
+            let mut buf = &b"\x06\x9a\x79\xf4\x44\xe9\x47\x26\xa5\xbe\xfc\xa9\x0e\x38\xaa\xf5"[..];
+            let val = parse_uuid(&mut buf).unwrap();
+            assert_eq!(format_uuid(val), "069a79f4-44e9-4726-a5be-fca90e38aaf5");
+        }
+
+        #[test]
+        fn format_keeps_leading_zeros() {
+            assert_eq!(format_uuid(1), "00000000-0000-0000-0000-000000000001");
+        }
+
+        #[test]
+        fn reject_15_bytes() {
+            let mut buf = &b"\x06\x9a\x79\xf4\x44\xe9\x47\x26\xa5\xbe\xfc\xa9\x0e\x38\xaa"[..];
+            let val = parse_uuid(&mut buf).err().unwrap();
+            assert_eq!(val, MinecraftParseError::UnexpectedEof);
+        }
+    }
+
+    mod parse_position {
+        use crate::*;
+
+        #[test]
+        fn parse_positive_and_negative_fields() {
+            let mut buf = &b"\x46\x07\x63\x2c\x15\xb4\x83\x3f"[..];
+            let val = parse_position(&mut buf).unwrap();
+            assert_eq!(val, Position { x: 18357644, y: 831, z: -20882616 });
+        }
+
+        #[test]
+        fn parse_negative_x_and_y() {
+            let mut buf = &b"\xff\xff\xff\xc0\x00\x00\x5f\xc0"[..];
+            let val = parse_position(&mut buf).unwrap();
+            assert_eq!(val, Position { x: -1, y: -64, z: 5 });
+        }
+
+        #[test]
+        fn parse_field_extremes() {
+            let mut buf = &b"\x80\x00\x00\x1f\xff\xff\xf8\x00"[..];
+            let val = parse_position(&mut buf).unwrap();
+            assert_eq!(val, Position { x: -33554432, y: -2048, z: 33554431 });
+        }
+
+        #[test]
+        fn reject_short_buffer() {
+            let mut buf = &b"\x46\x07\x63\x2c"[..];
+            let val = parse_position(&mut buf).err().unwrap();
+            assert_eq!(val, MinecraftParseError::UnexpectedEof);
+        }
+    }
+
+    mod parse_bitset {
+        use crate::*;
+
+        #[test]
+        fn index_into_words() {
+            // words 0b101 and i64::MIN, i.e. bits 0, 2 and 127 set
+            let mut buf = &b"\x02\x00\x00\x00\x00\x00\x00\x00\x05\x80\x00\x00\x00\x00\x00\x00\x00"[..];
+            let val = parse_bitset(&mut buf).unwrap();
+            assert_eq!(val.words(), &[5, i64::MIN]);
+            assert!(val.get(0));
+            assert!(!val.get(1));
+            assert!(val.get(2));
+            assert!(!val.get(63));
+            assert!(!val.get(64));
+            assert!(val.get(127));
+            assert!(!val.get(128));
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn parse_empty() {
+            let mut buf = &b"\x00"[..];
+            let val = parse_bitset(&mut buf).unwrap();
+            assert!(!val.get(0));
+        }
+
+        #[test]
+        fn reject_truncated_word() {
+            let mut buf = &b"\x01\x00\x00\x00\x00"[..];
+            let val = parse_bitset(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+    }
+
+    mod parse_identifier {
+        use crate::*;
+
+        #[test]
+        fn parse_namespaced() {
+            let mut buf = &b"\x12mymod:blocks/thing"[..];
+            let val = parse_identifier(&mut buf).unwrap();
+            assert_eq!(val.namespace(), "mymod");
+            assert_eq!(val.path(), "blocks/thing");
+        }
+
+        #[test]
+        fn parse_bare_path() {
+            let mut buf = &b"\x05stone"[..];
+            let val = parse_identifier(&mut buf).unwrap();
+            assert_eq!(val.namespace(), "minecraft");
+            assert_eq!(val.path(), "stone");
+            assert_eq!(val.to_string(), "minecraft:stone");
+        }
+
+        #[test]
+        fn split_on_first_colon_only() {
+            let mut buf = &b"\x05a:b:c"[..];
+            let val = parse_identifier(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::InvalidIdentifier(ref s) if s == "a:b:c"));
+        }
+
+        #[test]
+        fn reject_slash_in_namespace() {
+            let mut buf = &b"\x07a/b:foo"[..];
+            let val = parse_identifier(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::InvalidIdentifier(_)));
+        }
+
+        #[test]
+        fn reject_uppercase() {
+            let mut buf = &b"\x0fminecraft:Stone"[..];
+            let val = parse_identifier(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::InvalidIdentifier(_)));
+        }
+    }
+
+    mod parse_angle {
+        use crate::*;
+
+        #[test]
+        fn parse_quarter_turn() {
+            let mut buf = &b"\x40"[..];
+            assert_eq!(parse_angle(&mut buf).unwrap(), 90.0);
+        }
+
+        #[test]
+        fn parse_range_ends() {
+            let mut buf = &b"\x00\x80\xff"[..];
+            assert_eq!(parse_angle(&mut buf).unwrap(), 0.0);
+            assert_eq!(parse_angle(&mut buf).unwrap(), 180.0);
+            let val = parse_angle(&mut buf).unwrap();
+            assert!(val < 360.0 && val > 358.0);
+        }
+
+        #[test]
+        fn reject_empty_buffer() {
+            let mut buf = &b""[..];
+            let val = parse_angle(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+    }
+
+    mod parse_string_max_chars {
+        use crate::*;
+
+        #[test]
+        fn accept_name_at_limit() {
+            let mut buf = &b"\x10ABCDEFGHIJKLMNOP"[..];
+            let val = parse_string_max_chars(&mut buf, 16).unwrap();
+            assert_eq!(val, "ABCDEFGHIJKLMNOP");
+        }
+
+        #[test]
+        fn reject_17_char_name() {
+            let mut buf = &b"\x11ABCDEFGHIJKLMNOPQ"[..];
+            let val = parse_string_max_chars(&mut buf, 16).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::StringTooManyChars { max: 16 }));
+        }
+
+        #[test]
+        fn accept_multi_byte_name_within_limit() {
+            // 16 characters in 48 bytes, over the 16 bytes a byte cap would allow
+            let name = "\u{3042}".repeat(16);
+            let mut packet = vec![name.len() as u8];
+            packet.extend_from_slice(name.as_bytes());
+            let val = parse_string_max_chars(&mut &packet[..], 16).unwrap();
+            assert_eq!(val, name);
+        }
+
+        #[test]
+        fn reject_bytes_over_3_per_char() {
+            let mut buf = &b"\x31"[..];
+            let val = parse_string_max_chars(&mut buf, 16).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::StringTooLong { max: 48 }));
+        }
+
+        #[test]
+        fn count_utf16_units() {
+            // U+1F600 takes two UTF-16 code units
+            let mut buf = &b"\x05\xf0\x9f\x98\x80a"[..];
+            let val = parse_string_max_chars(&mut buf, 2).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::StringTooManyChars { max: 2 }));
+        }
+    }
+
+    mod parse_string_n {
+        use crate::*;
+
+        #[test]
+        fn parse_string() {
+            let mut buf = &b"\x0c\x31\x32\x33\x2e\x34\x35\x2e\x36\x37\x2e\x38\x39"[..];
+            let val = parse_string_n(&mut buf).unwrap();
+            assert_eq!(val, "123.45.67.89".to_string());
+        }
+        
+        #[test]
+        fn reject_invalid_string() {
+            let mut buf = &b"\x0f\xf1\x36\x30\x2e\x32\x35\x31\x2e\x31\x30\x30\x2e\x32\x34\x39"[..];
+            let val = parse_string_n(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::InvalidStringEncoding(_)));
+        }
+        
+        #[test]
+        fn reject_short_string() {
+            let mut buf = &b"\x03\x31\x36"[..];
+            let val = parse_string_n(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::StringTooShort));
+        }
+
+        #[test]
+        fn reject_empty() {
+            let mut buf = &b""[..];
+            let val = parse_string_n(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+
+        #[test]
+        fn encode_string() {
+            let mut out = Vec::new();
+            encode_string_n("123.45.67.89", &mut out);
+            assert_eq!(out, b"\x0c\x31\x32\x33\x2e\x34\x35\x2e\x36\x37\x2e\x38\x39");
+        }
+
+        #[test]
+        fn encode_multi_byte_string_with_byte_length() {
+            let mut out = Vec::new();
+            encode_string_n("\u{3042}", &mut out);
+            assert_eq!(out, b"\x03\xe3\x81\x82");
+        }
+
+        #[test]
+        fn reject_length_over_cap() {
+            let mut buf = &b"\x0c\x31\x32\x33\x2e\x34\x35\x2e\x36\x37\x2e\x38\x39"[..];
+            let val = parse_string_n_capped(&mut buf, 11).err().unwrap();
+            assert_eq!(val, MinecraftParseError::StringTooLong { max: 11 });
+        }
+
+        #[test]
+        fn accept_length_at_cap() {
+            let mut buf = &b"\x0c\x31\x32\x33\x2e\x34\x35\x2e\x36\x37\x2e\x38\x39"[..];
+            let val = parse_string_n_capped(&mut buf, 12).unwrap();
+            assert_eq!(val, "123.45.67.89");
+        }
+
+        #[test]
+        fn reject_huge_declared_length_before_reading() {
+            let mut buf = &b"\xff\xff\xff\xff\x07\x31"[..];
+            let val = parse_string_n(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::StringTooLong { .. }));
+        }
+
+        #[test]
+        fn distinguish_over_cap_from_truncated() {
+            // 98302 declared: one past the cap, so too long even though truncated
+            let mut buf = &b"\xfe\xff\x05\x31"[..];
+            let val = parse_string_n(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::StringTooLong { max: STRING_MAX_BYTES }));
+
+            // 98301 declared: within the cap, so merely truncated
+            let mut buf = &b"\xfd\xff\x05\x31"[..];
+            let val = parse_string_n(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::StringTooShort));
+        }
+
+        #[test]
+        fn reject_negative_declared_length() {
+            let mut buf = &b"\xff\xff\xff\xff\x0f\x31"[..];
+            let val = parse_string_n(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::StringTooLong { .. }));
+        }
+    }
+
+    mod parse_byte_array {
+        use crate::*;
+
+        #[test]
+        fn parse_array() {
+            let mut buf = &b"\x03\x01\x02\x03\x04"[..];
+            let val = parse_byte_array(&mut buf).unwrap();
+            assert_eq!(val, vec![1, 2, 3]);
+            assert_eq!(buf, b"\x04");
+        }
+
+        #[test]
+        fn parse_empty_array() {
+            let mut buf = &b"\x00"[..];
+            let val = parse_byte_array(&mut buf).unwrap();
+            assert!(val.is_empty());
+        }
+
+        #[test]
+        fn reject_short_array() {
+            let mut buf = &b"\x04\x01\x02\x03"[..];
+            let val = parse_byte_array(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::ArrayTooShort));
+        }
+
+        #[test]
+        fn reject_missing_length() {
+            let mut buf = &b""[..];
+            let val = parse_byte_array(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+    }
+
+    mod parse_array {
+        use crate::*;
+
+        #[test]
+        fn parse_3_varints() {
+            let mut buf = &b"\x03\x01\xac\x02\xff\xff\xff\xff\x0f"[..];
+            let val = parse_array(&mut buf, parse_varint).unwrap();
+            assert_eq!(val, vec![1, 300, -1]);
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn parse_empty() {
+            let mut buf = &b"\x00\x01"[..];
+            let val = parse_array(&mut buf, parse_varint).unwrap();
+            assert!(val.is_empty());
+            assert_eq!(buf, b"\x01");
+        }
+
+        #[test]
+        fn reject_huge_count_before_reading() {
+            // 1 << 20 + 1 declared, no elements present
+            let mut buf = &b"\x81\x80\x40"[..];
+            let val = parse_array(&mut buf, |_| -> Result<(), _> { panic!("should not be called") }).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::ArrayTooLarge(1048577)));
+        }
+
+        #[test]
+        fn reject_negative_count() {
+            let mut buf = &b"\xff\xff\xff\xff\x0f"[..];
+            let val = parse_array(&mut buf, parse_varint).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::ArrayTooLarge(-1)));
+        }
+
+        #[test]
+        fn reject_truncated_element() {
+            let mut buf = &b"\x02\x01"[..];
+            let val = parse_array(&mut buf, parse_varint).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+    }
+
+    mod parse_optional {
+        use crate::*;
+
+        #[test]
+        fn parse_present() {
+            let mut buf = &b"\x01\x03sig\x2a"[..];
+            let val = parse_optional(&mut buf, |b| parse_string_n(b)).unwrap();
+            assert_eq!(val.as_deref(), Some("sig"));
+            assert_eq!(buf, b"\x2a");
+        }
+
+        #[test]
+        fn parse_absent_without_calling_parser() {
+            let mut buf = &b"\x00\x03sig"[..];
+            let val = parse_optional(&mut buf, |_| -> Result<String, _> { panic!("should not be called") }).unwrap();
+            assert_eq!(val, None);
+            assert_eq!(buf, b"\x03sig");
+        }
+
+        #[test]
+        fn reject_non_bool_prefix() {
+            let mut buf = &b"\x02\x03sig"[..];
+            let val = parse_optional(&mut buf, parse_string_n).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::InvalidBool(0x02)));
+        }
+
+        #[test]
+        fn propagate_inner_error() {
+            let mut buf = &b"\x01\x03si"[..];
+            let val = parse_optional(&mut buf, parse_string_n).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::StringTooShort));
+        }
+    }
+
+    mod parse_handshake {
+        use crate::*;
+
+        #[test]
+        fn parse_good_packet() {
+            let mut buf = test_util::SAMPLE_HANDSHAKE;
+            let val = parse_handshake(&mut buf).unwrap();
+            assert_eq!(val, Handshake {
+                protocol_version: 754,
+                address: "123.45.67.89".to_string(),
+                port: 25565,
+                next_state: NextState::Login,
+            });
+        }
+
+        #[test]
+        fn read_fields_through_accessors() {
+            let mut buf = test_util::SAMPLE_HANDSHAKE;
+            let val = parse_handshake(&mut buf).unwrap();
+            assert_eq!(val.protocol_version(), 754);
+            assert_eq!(val.address(), "123.45.67.89");
+            assert_eq!(val.port(), 25565);
+            assert_eq!(val.next_state(), NextState::Login);
+            assert_eq!(val.minecraft_version(), Some("1.16.5"));
+        }
+
+        fn sample_with_next_state(next_state: i32) -> Vec<u8> {
+            test_util::build_handshake_bytes(754, "123.45.67.89", 25565, next_state)
+        }
+
+        #[test]
+        fn parse_next_state_status() {
+            let packet = sample_with_next_state(1);
+            let val = parse_handshake(&mut &packet[..]).unwrap();
+            assert_eq!(val.next_state(), NextState::Status);
+        }
+
+        #[test]
+        fn parse_next_state_login() {
+            let packet = sample_with_next_state(2);
+            let val = parse_handshake(&mut &packet[..]).unwrap();
+            assert_eq!(val.next_state(), NextState::Login);
+        }
+
+        #[test]
+        fn parse_next_state_transfer() {
+            let packet = sample_with_next_state(3);
+            let val = parse_handshake(&mut &packet[..]).unwrap();
+            assert_eq!(val.next_state(), NextState::Transfer);
+            assert!(val.is_transfer());
+        }
+
+        #[test]
+        fn expect_packet_for_each_next_state() {
+            let expected = [
+                (1, ExpectedPacket::StatusRequest),
+                (2, ExpectedPacket::LoginStart),
+                (3, ExpectedPacket::TransferLogin),
+            ];
+            for (next_state, packet) in expected {
+                let val = parse_handshake(&mut &sample_with_next_state(next_state)[..]).unwrap();
+                assert_eq!(val.expected_next_packet(), packet);
+            }
+        }
+
+        #[test]
+        fn login_is_not_transfer() {
+            let packet = sample_with_next_state(2);
+            let val = parse_handshake(&mut &packet[..]).unwrap();
+            assert!(!val.is_transfer());
+        }
+
+        #[test]
+        fn reject_unknown_next_state() {
+            let packet = sample_with_next_state(4);
+            let val = parse_handshake(&mut &packet[..]).err().unwrap();
+            assert_eq!(val, MinecraftParseError::InvalidNextState(4));
+        }
+
+        #[test]
+        fn leave_trailing_bytes_in_buffer() {
+            let packet = b"\x13\x00\xf2\x05\x0c\x31\x32\x33\x2e\x34\x35\x2e\x36\x37\x2e\x38\x39\x63\xdd\x01\x01\x00";
+            let mut buf = &packet[..];
+            let val = parse_handshake(&mut buf).unwrap();
+            assert_eq!(val.next_state(), NextState::Status);
+            assert_eq!(buf, b"\x01\x00");
+        }
+
+        #[test]
+        fn name_field_truncated_before_port() {
+            let mut buf = &b"\x10\x00\xf2\x05\x0c123.45.67.89"[..];
+            let val = parse_handshake(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::TruncatedField("port")));
+        }
+
+        #[test]
+        fn name_field_truncated_inside_address() {
+            let mut buf = &b"\x08\x00\xf2\x05\x0c123.45"[..];
+            let val = parse_handshake(&mut buf).err().unwrap();
+            assert_eq!(val, MinecraftParseError::TruncatedField("address"));
+        }
+
+        #[test]
+        fn name_field_truncated_before_next_state() {
+            let mut buf = &b"\x12\x00\xf2\x05\x0c123.45.67.89\x63\xdd"[..];
+            let val = parse_handshake(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::TruncatedField("next_state")));
+        }
+
+        #[test]
+        fn reject_packet_shorter_than_declared() {
+            let mut buf = &b"\x14\x00\xf2\x05\x0c\x31\x32\x33\x2e\x34\x35\x2e\x36\x37\x2e\x38\x39\x63\xdd\x02"[..];
+            let val = parse_handshake(&mut buf).err().unwrap();
+            assert_eq!(val, MinecraftParseError::LengthNotMatch);
+        }
+
+        #[test]
+        fn reject_body_longer_than_fields() {
+            let mut buf = &b"\x14\x00\xf2\x05\x0c\x31\x32\x33\x2e\x34\x35\x2e\x36\x37\x2e\x38\x39\x63\xdd\x02\x00"[..];
+            let val = parse_handshake(&mut buf).err().unwrap();
+            assert_eq!(val, MinecraftParseError::LengthNotMatch);
+        }
+
+        #[test]
+        fn reject_address_over_255_bytes() {
+            // body: id, version, 256-byte address, port, next_state = 264 bytes
+            let mut packet = b"\x88\x02\x00\xf2\x05\x80\x02".to_vec();
+            packet.extend_from_slice("a".repeat(256).as_bytes());
+            packet.extend_from_slice(b"\x63\xdd\x02");
+            let val = parse_handshake(&mut &packet[..]).err().unwrap();
+            assert_eq!(val, MinecraftParseError::StringTooLong { max: 255 });
+        }
+
+        #[test]
+        fn reject_truncated_port() {
+            let mut buf = &b"\x11\x00\xf2\x05\x0c\x31\x32\x33\x2e\x34\x35\x2e\x36\x37\x2e\x38\x39\x63"[..];
+            let val = parse_handshake(&mut buf).err().unwrap();
+            assert_eq!(val, MinecraftParseError::TruncatedField("port"));
+        }
+    }
+
+    mod peek_packet_length {
+        use crate::*;
+
+        #[test]
+        fn peek_complete_prefix() {
+            let buf = &b"\x13\x00\xf2"[..];
+            let val = peek_packet_length(buf).unwrap();
+            assert_eq!(val, Some((19, 1)));
+        }
+
+        #[test]
+        fn peek_multi_byte_prefix() {
+            let buf = &b"\xf2\x05"[..];
+            let val = peek_packet_length(buf).unwrap();
+            assert_eq!(val, Some((754, 2)));
+        }
+
+        #[test]
+        fn none_for_truncated_prefix() {
+            let buf = &b"\xf2"[..];
+            let val = peek_packet_length(buf).unwrap();
+            assert_eq!(val, None);
+        }
+
+        #[test]
+        fn none_for_empty() {
+            let val = peek_packet_length(&b""[..]).unwrap();
+            assert_eq!(val, None);
+        }
+
+        #[test]
+        fn reject_corrupted_prefix() {
+            let buf = &b"\xff\xff\xff\xff\xff\x01"[..];
+            let val = peek_packet_length(buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::InvalidVarInt));
+        }
+    }
+
+    mod parse_handshake_limited {
+        use crate::*;
+
+        use crate::test_util::SAMPLE_HANDSHAKE as PACKET;
+
+        #[test]
+        fn accept_packet_at_cap() {
+            let mut buf = PACKET;
+            let val = parse_handshake_limited(&mut buf, 0x13).unwrap();
+            assert_eq!(val.port(), 25565);
+        }
+
+        #[test]
+        fn reject_packet_over_cap() {
+            let mut buf = PACKET;
+            let val = parse_handshake_limited(&mut buf, 0x12).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::PacketTooLarge { len: 0x13, max: 0x12 }));
+        }
+
+        #[test]
+        fn default_cap_is_vanilla_maximum() {
+            // 2097152 declared, nothing following
+            let mut buf = &b"\x80\x80\x80\x01"[..];
+            let val = parse_handshake(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::PacketTooLarge { len: 2097152, max: MAX_PACKET_LEN }));
+        }
+    }
+
+    mod try_parse_handshake {
+        use crate::*;
+
+        use crate::test_util::SAMPLE_HANDSHAKE as PACKET;
+
+        #[test]
+        fn complete_packet() {
+            let val = try_parse_handshake(PACKET).unwrap();
+            assert_eq!(val, ParseOutcome::Complete {
+                handshake: Handshake::new(754, "123.45.67.89", 25565, NextState::Login),
+                consumed: 20,
+            });
+        }
+
+        #[test]
+        fn complete_packet_with_trailing_bytes() {
+            let mut packet = PACKET.to_vec();
+            packet.extend_from_slice(b"\x01\x00");
+            let val = try_parse_handshake(&packet).unwrap();
+            assert!(matches!(val, ParseOutcome::Complete { consumed: 20, .. }));
+        }
+
+        #[test]
+        fn body_needs_more_bytes() {
+            let val = try_parse_handshake(&PACKET[..12]).unwrap();
+            assert_eq!(val, ParseOutcome::Incomplete { needed: Some(8) });
+        }
+
+        #[test]
+        fn length_prefix_incomplete() {
+            assert_eq!(try_parse_handshake(b"").unwrap(), ParseOutcome::Incomplete { needed: None });
+            assert_eq!(try_parse_handshake(b"\x93").unwrap(), ParseOutcome::Incomplete { needed: None });
+        }
+
+        #[test]
+        fn oversized_packet_is_not_incomplete() {
+            let val = try_parse_handshake(b"\x80\x80\x80\x01").err().unwrap();
+            assert!(matches!(val, MinecraftParseError::PacketTooLarge { .. }));
+        }
+
+        #[test]
+        fn protocol_error_is_not_incomplete() {
+            let val = try_parse_handshake(b"\x01\x01").err().unwrap();
+            assert!(matches!(val, MinecraftParseError::NotHandshake));
+        }
+    }
+
+    mod parse_handshake_safe {
+        use crate::*;
+
+        use crate::test_util::SAMPLE_HANDSHAKE as PACKET;
+
+        #[test]
+        fn parse_sample() {
+            let val = parse_handshake_safe(PACKET).unwrap();
+            assert_eq!(val, Handshake::new(754, "123.45.67.89", 25565, NextState::Login));
+        }
+
+        #[test]
+        fn every_truncation_is_an_error() {
+            for end in 0..PACKET.len() {
+                assert!(parse_handshake_safe(&PACKET[..end]).is_err(), "prefix of {} bytes", end);
+            }
+        }
+
+        #[test]
+        fn single_byte_mutations_do_not_panic() {
+            for i in 0..PACKET.len() {
+                for byte in [0x00, 0x7f, 0x80, 0xff] {
+                    let mut packet = PACKET.to_vec();
+                    packet[i] = byte;
+                    let _ = parse_handshake_safe(&packet);
+                }
+            }
+        }
+    }
+
+    mod parse_handshake_with_rest {
+        use crate::*;
+
+        use crate::test_util::SAMPLE_HANDSHAKE as PACKET;
+
+        #[test]
+        fn return_appended_bytes() {
+            let mut packet = PACKET.to_vec();
+            packet.extend_from_slice(b"\x01\x00\xff");
+            let (val, rest) = parse_handshake_with_rest(&packet).unwrap();
+            assert_eq!(val.next_state(), NextState::Login);
+            assert_eq!(rest, b"\x01\x00\xff");
+        }
+
+        #[test]
+        fn return_empty_tail() {
+            let (_, rest) = parse_handshake_with_rest(PACKET).unwrap();
+            assert!(rest.is_empty());
+        }
+
+        #[test]
+        fn reject_truncated_packet() {
+            let val = parse_handshake_with_rest(&PACKET[..10]).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::LengthNotMatch));
+        }
+    }
+
+    mod packet_iter {
+        use crate::*;
+
+        #[test]
+        fn yield_each_body() {
+            let mut iter = PacketIter::new(b"\x01\x00\x09\x01\x00\x00\x00\x00\x00\x00\x00\x2a");
+            assert_eq!(iter.next().unwrap().unwrap(), b"\x00");
+            assert_eq!(iter.next().unwrap().unwrap(), b"\x01\x00\x00\x00\x00\x00\x00\x00\x2a");
+            assert!(iter.next().is_none());
+            assert!(iter.remainder().is_empty());
+        }
+
+        #[test]
+        fn stop_on_partial_packet() {
+            let mut iter = PacketIter::new(b"\x01\x00\x09\x01\x00\x00");
+            assert_eq!(iter.next().unwrap().unwrap(), b"\x00");
+            assert!(iter.next().is_none());
+            assert_eq!(iter.remainder(), b"\x09\x01\x00\x00");
+        }
+
+        #[test]
+        fn stop_after_malformed_length() {
+            let mut iter = PacketIter::new(b"\xff\xff\xff\xff\xff\x01\x00");
+            assert!(matches!(iter.next(), Some(Err(MinecraftParseError::InvalidVarInt))));
+            assert!(iter.next().is_none());
+        }
+    }
+
+    mod frame_packets {
+        use crate::*;
+
+        const PACKETS: &[u8] = b"\x13\x00\xf2\x05\x0c123.45.67.89\x63\xdd\x02\x01\x00";
+
+        #[test]
+        fn split_two_packets() {
+            let mut buf = PACKETS;
+            let val = frame_packets(&mut buf).unwrap();
+            assert_eq!(val.len(), 2);
+            assert_eq!(val[0].0, 0);
+            assert_eq!(&val[0].1[..], &b"\xf2\x05\x0c123.45.67.89\x63\xdd\x02"[..]);
+            assert_eq!(val[1].0, 0);
+            assert!(val[1].1.is_empty());
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn leave_partial_packet() {
+            let mut buf = &PACKETS[..PACKETS.len() - 1];
+            let val = frame_packets(&mut buf).unwrap();
+            assert_eq!(val.len(), 1);
+            assert_eq!(buf, b"\x01");
+        }
+
+        #[test]
+        fn reject_empty_frame() {
+            let mut buf = &b"\x00"[..];
+            let val = frame_packets(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+    }
+
+    mod parse_handshakes {
+        use crate::*;
+
+        use crate::test_util::SAMPLE_HANDSHAKE as PACKET;
+
+        #[test]
+        fn parse_two_concatenated() {
+            let mut packet = PACKET.to_vec();
+            encode_handshake(&Handshake::new(340, "localhost", 25565, NextState::Status), &mut packet);
+            let mut buf = &packet[..];
+            let val = parse_handshakes(&mut buf).unwrap();
+            assert_eq!(val, vec![
+                Handshake::new(754, "123.45.67.89", 25565, NextState::Login),
+                Handshake::new(340, "localhost", 25565, NextState::Status),
+            ]);
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn leave_partial_trailing_packet() {
+            let mut packet = PACKET.to_vec();
+            packet.extend_from_slice(&PACKET[..7]);
+            let mut buf = &packet[..];
+            let val = parse_handshakes(&mut buf).unwrap();
+            assert_eq!(val.len(), 1);
+            assert_eq!(buf, &PACKET[..7]);
+        }
+
+        #[test]
+        fn reject_invalid_handshake() {
+            let mut packet = PACKET.to_vec();
+            packet.extend_from_slice(b"\x01\x01");
+            let val = parse_handshakes(&mut &packet[..]).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::NotHandshake));
+        }
+    }
+
+    mod minecraft_parse_error {
+        use crate::*;
+
+        #[test]
+        fn compare_payloads() {
+            assert_eq!(MinecraftParseError::UnexpectedPacketId(1), MinecraftParseError::UnexpectedPacketId(1));
+            assert_ne!(MinecraftParseError::UnexpectedPacketId(1), MinecraftParseError::UnexpectedPacketId(2));
+            assert_ne!(MinecraftParseError::UnexpectedEof, MinecraftParseError::LengthNotMatch);
+        }
+
+        #[cfg(feature = "std")]
+        #[test]
+        fn compare_io_by_kind() {
+            let eof = |msg| MinecraftParseError::from(io::Error::new(io::ErrorKind::UnexpectedEof, msg));
+            assert_eq!(eof("first"), eof("second"));
+            assert_ne!(eof("first"), MinecraftParseError::from(io::Error::from(io::ErrorKind::BrokenPipe)));
+            assert_ne!(eof("first"), MinecraftParseError::Decompression(io::Error::from(io::ErrorKind::UnexpectedEof)));
+        }
+    }
+
+    #[cfg(feature = "std")]
+    mod parse_handshake_from_reader {
+        use crate::*;
+        use std::io::Cursor;
+
+        use crate::test_util::SAMPLE_HANDSHAKE as PACKET;
+
+        #[test]
+        fn parse_from_cursor() {
+            let mut reader = Cursor::new(PACKET);
+            let val = parse_handshake_from_reader(&mut reader).unwrap();
+            assert_eq!(val, parse_handshake(&mut &PACKET[..]).unwrap());
+            assert_eq!(reader.position(), PACKET.len() as u64);
+        }
+
+        #[test]
+        fn leave_following_bytes_unread() {
+            let mut packet = PACKET.to_vec();
+            packet.extend_from_slice(b"\x01\x00");
+            let mut reader = Cursor::new(packet);
+            parse_handshake_from_reader(&mut reader).unwrap();
+            assert_eq!(reader.position(), PACKET.len() as u64);
+        }
+
+        #[test]
+        fn reject_truncated_length_prefix() {
+            let mut reader = Cursor::new(&b"\xf2"[..]);
+            let val = parse_handshake_from_reader(&mut reader).err().unwrap();
+            assert_eq!(val, MinecraftParseError::Io(io::ErrorKind::UnexpectedEof.into()));
+        }
+
+        #[test]
+        fn reject_oversized_length_before_reading() {
+            let mut reader = Cursor::new(&b"\x80\x80\x80\x01\x00"[..]);
+            let val = parse_handshake_from_reader(&mut reader).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::PacketTooLarge { .. }));
+            assert_eq!(reader.position(), 4);
+        }
+
+        #[test]
+        fn reject_too_long_length_prefix() {
+            let mut reader = Cursor::new(&b"\xff\xff\xff\xff\xff\x01"[..]);
+            let val = parse_handshake_from_reader(&mut reader).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::InvalidVarInt));
+        }
+
+        #[test]
+        fn reject_truncated_body() {
+            let mut reader = Cursor::new(&PACKET[..10]);
+            let val = parse_handshake_from_reader(&mut reader).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+    }
+
+    mod parse_ctx {
+        use crate::*;
+
+        #[test]
+        fn track_position() {
+            let mut ctx = ParseCtx::new(b"\xf2\x05\x03abc\x63\xdd");
+            assert_eq!(ctx.varint().unwrap(), 754);
+            assert_eq!(ctx.pos(), 2);
+            assert_eq!(ctx.string().unwrap(), "abc");
+            assert_eq!(ctx.pos(), 6);
+            assert_eq!(ctx.ushort().unwrap(), 25565);
+            assert_eq!(ctx.remaining(), 0);
+        }
+
+        #[test]
+        fn report_offset_of_failed_field() {
+            let mut ctx = ParseCtx::new(b"\x01\x05ab");
+            ctx.varint().unwrap();
+            let val = ctx.string().err().unwrap();
+            assert_eq!(val.offset(), Some(1));
+            assert_eq!(val.into_inner(), MinecraftParseError::StringTooShort);
+            // a failed read consumes nothing
+            assert_eq!(ctx.pos(), 1);
+        }
+    }
+
+    mod parse_handshake_frame {
+        use crate::*;
+
+        use crate::test_util::SAMPLE_HANDSHAKE as PACKET;
+
+        #[test]
+        fn parse_sample() {
+            let mut packet = PACKET.to_vec();
+            packet.push(0x01);
+            let mut ctx = ParseCtx::new(&packet);
+            let val = parse_handshake_frame(&mut ctx, MAX_PACKET_LEN).unwrap();
+            assert_eq!(val, parse_handshake(&mut &PACKET[..]).unwrap());
+            assert_eq!(ctx.pos(), PACKET.len());
+        }
+
+        #[test]
+        fn parse_handshake_chained_buffer() {
+            let mut packet = PACKET.to_vec();
+            packet.push(0x01);
+            let mut chained = Buf::chain(&packet[..7], &packet[7..]);
+            let val = parse_handshake(&mut chained).unwrap();
+            assert_eq!(val, parse_handshake(&mut &PACKET[..]).unwrap());
+            assert_eq!(chained.remaining(), 1);
+        }
+
+        #[test]
+        fn report_oversized_second_packet_at_its_length() {
+            let mut packet = PACKET.to_vec();
+            packet.extend_from_slice(b"\xff\xff\xff\x07\x00");
+            let mut ctx = ParseCtx::new(&packet);
+            parse_handshake_frame(&mut ctx, MAX_PACKET_LEN).unwrap();
+            let val = parse_handshake_frame(&mut ctx, MAX_PACKET_LEN).err().unwrap();
+            assert_eq!(val.offset(), Some(PACKET.len()));
+            assert!(matches!(val.into_inner(), MinecraftParseError::PacketTooLarge { .. }));
+        }
+
+        #[test]
+        fn report_truncated_port() {
+            let mut ctx = ParseCtx::new(b"\x11\x00\xf2\x05\x0c123.45.67.89\x63");
+            let val = parse_handshake_frame(&mut ctx, MAX_PACKET_LEN).err().unwrap();
+            assert_eq!(val, MinecraftParseError::AtOffset { offset: 17, source: Box::new(MinecraftParseError::TruncatedField("port")) });
+        }
+
+        #[test]
+        fn report_invalid_next_state() {
+            let mut ctx = ParseCtx::new(b"\x13\x00\xf2\x05\x0c123.45.67.89\x63\xdd\x05");
+            let val = parse_handshake_frame(&mut ctx, MAX_PACKET_LEN).err().unwrap();
+            assert_eq!(val.offset(), Some(19));
+            assert_eq!(val.into_inner(), MinecraftParseError::InvalidNextState(5));
+        }
+
+        #[test]
+        fn report_other_packet_id() {
+            let mut ctx = ParseCtx::new(b"\x01\x01");
+            let val = parse_handshake_frame(&mut ctx, MAX_PACKET_LEN).err().unwrap();
+            assert_eq!(val.offset(), Some(1));
+            assert_eq!(val.into_inner(), MinecraftParseError::NotHandshake);
+        }
+
+        #[test]
+        fn report_trailing_body_bytes() {
+            let mut ctx = ParseCtx::new(b"\x14\x00\xf2\x05\x0c123.45.67.89\x63\xdd\x02\x00");
+            let val = parse_handshake_frame(&mut ctx, MAX_PACKET_LEN).err().unwrap();
+            assert_eq!(val.offset(), Some(20));
+            assert_eq!(val.into_inner(), MinecraftParseError::LengthNotMatch);
+        }
+
+        #[test]
+        fn match_parse_handshake_errors() {
+            let inputs: &[&[u8]] = &[
+                b"",
+                b"\x00",
+                b"\x01\x01",
+                b"\x02\x00\xf2",
+                b"\x05\x00\xf2\x05\x0c1",
+                b"\x11\x00\xf2\x05\x0c123.45.67.89\x63",
+                b"\x13\x00\xf2\x05\x0c123.45.67.89\x63\xdd\x05",
+                b"\x14\x00\xf2\x05\x0c123.45.67.89\x63\xdd\x02\x00",
+                b"\x13\x00\xf2\x05\x0c123",
+                b"\xff\xff\xff\x07\x00",
+            ];
+            for input in inputs {
+                let val = parse_handshake_frame(&mut ParseCtx::new(input), MAX_PACKET_LEN).err().unwrap();
+                // a chained buffer takes the frame reader path
+                let mid = input.len() / 2;
+                let mut chained = Buf::chain(&input[..mid], &input[mid..]);
+                let expected = parse_handshake(&mut chained).err().unwrap();
+                assert_eq!(val.into_inner(), expected, "input {:?}", input);
+                assert_eq!(parse_handshake(&mut &input[..]).err().unwrap(), expected, "input {:?}", input);
+            }
+        }
+
+        #[test]
+        fn display_offset() {
+            let mut ctx = ParseCtx::new(b"\x01\x01");
+            let val = parse_handshake_frame(&mut ctx, MAX_PACKET_LEN).err().unwrap();
+            assert_eq!(val.to_string(), "This packet is not for handshaking at byte 1");
+        }
+    }
+
+    mod parse_handshake_ref {
+        use crate::*;
+
+        use crate::test_util::SAMPLE_HANDSHAKE as PACKET;
+
+        #[test]
+        fn parse_good_packet() {
+            let val = parse_handshake_ref(PACKET).unwrap();
+            assert_eq!(val.protocol_version(), 754);
+            assert_eq!(val.address(), "123.45.67.89");
+            assert_eq!(val.port(), 25565);
+            assert_eq!(val.next_state(), NextState::Login);
+        }
+
+        #[test]
+        fn borrow_address_from_input() {
+            let val = parse_handshake_ref(PACKET).unwrap();
+            assert_eq!(val.address().as_ptr(), PACKET[5..].as_ptr());
+        }
+
+        #[test]
+        fn convert_to_owned() {
+            let val = parse_handshake_ref(PACKET).unwrap().to_owned();
+            assert_eq!(val, parse_handshake(&mut &PACKET[..]).unwrap());
+        }
+
+        #[test]
+        fn reject_invalid_utf8_address() {
+            let val = parse_handshake_ref(b"\x07\x00\xf2\x05\x01\xff\x63\xdd\x02").err().unwrap();
+            assert!(matches!(val, MinecraftParseError::InvalidStringEncoding(_)));
+        }
+
+        #[test]
+        fn reject_truncated_packet() {
+            let val = parse_handshake_ref(&PACKET[..10]).err().unwrap();
+            assert_eq!(val, MinecraftParseError::LengthNotMatch);
+        }
+
+        #[test]
+        fn match_parse_handshake_errors() {
+            for k in 0..PACKET.len() {
+                let input = &PACKET[..k];
+                assert_eq!(parse_handshake_ref(input).err(), parse_handshake(&mut &input[..]).err(), "prefix {}", k);
+            }
+            // frames whose declared length agrees with a cut-short body
+            let body = &PACKET[1..];
+            for k in 0..body.len() {
+                let mut input = vec![k as u8];
+                input.extend_from_slice(&body[..k]);
+                assert_eq!(parse_handshake_ref(&input).err(), parse_handshake(&mut &input[..]).err(), "body {}", k);
+            }
+            let input = b"\xff\xff\xff\x07\x00";
+            assert_eq!(parse_handshake_ref(input).err(), parse_handshake(&mut &input[..]).err());
+        }
+    }
+
+    mod parse_status_request {
+        use crate::*;
+
+        #[test]
+        fn parse_empty_request() {
+            let mut buf = &b"\x01\x00"[..];
+            parse_status_request(&mut buf).unwrap();
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn parse_request_followed_by_ping() {
+            let mut buf = &b"\x01\x00\x09\x01"[..];
+            parse_status_request(&mut buf).unwrap();
+            assert_eq!(buf, b"\x09\x01");
+        }
+
+        #[test]
+        fn reject_trailing_body() {
+            let mut buf = &b"\x02\x00\x00"[..];
+            let val = parse_status_request(&mut buf).err().unwrap();
+            assert_eq!(val, MinecraftParseError::LengthNotMatch);
+        }
+
+        #[test]
+        fn reject_other_packet_id() {
+            let mut buf = &b"\x01\x01"[..];
+            let val = parse_status_request(&mut buf).err().unwrap();
+            assert_eq!(val, MinecraftParseError::UnexpectedPacketId(0x01));
+        }
+
+        #[test]
+        fn reject_truncated_packet() {
+            let mut buf = &b"\x01"[..];
+            let val = parse_status_request(&mut buf).err().unwrap();
+            assert_eq!(val, MinecraftParseError::LengthNotMatch);
+        }
+    }
+
+    mod parse_status_flow {
+        use crate::*;
+        use crate::test_util::*;
+
+        const PING_REQUEST: &[u8] = b"\x09\x01\x00\x00\x00\x00\x00\x00\x00\x2a";
+
+        fn flow(next_state: i32) -> Vec<u8> {
+            let mut packets = build_handshake_bytes(754, "123.45.67.89", 25565, next_state);
+            packets.extend_from_slice(b"\x01\x00");
+            packets.extend_from_slice(PING_REQUEST);
+            packets
+        }
+
+        #[test]
+        fn parse_three_packets() {
+            let packets = flow(1);
+            let mut buf = &packets[..];
+            let (handshake, payload) = parse_status_flow(&mut buf).unwrap();
+            assert_eq!(handshake.address(), "123.45.67.89");
+            assert_eq!(handshake.next_state(), NextState::Status);
+            assert_eq!(payload, 42);
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn reject_login_handshake() {
+            let packets = flow(2);
+            let val = parse_status_flow(&mut &packets[..]).err().unwrap();
+            assert_eq!(val, MinecraftParseError::UnexpectedNextState(NextState::Login));
+        }
+
+        #[test]
+        fn reject_missing_status_request() {
+            let mut packets = build_handshake_bytes(754, "123.45.67.89", 25565, 1);
+            packets.extend_from_slice(PING_REQUEST);
+            let val = parse_status_flow(&mut &packets[..]).err().unwrap();
+            assert_eq!(val, MinecraftParseError::UnexpectedPacketId(0x01));
+        }
+
+        #[test]
+        fn reject_missing_ping_request() {
+            let mut packets = build_handshake_bytes(754, "123.45.67.89", 25565, 1);
+            packets.extend_from_slice(b"\x01\x00");
+            let val = parse_status_flow(&mut &packets[..]).err().unwrap();
+            assert_eq!(val, MinecraftParseError::UnexpectedEof);
+        }
+    }
+
+    mod parse_ping_request {
+        use crate::*;
+
+        #[test]
+        fn parse_payload() {
+            let mut buf = &b"\x09\x01\x00\x00\x00\x00\x00\x00\x00\x2a"[..];
+            let val = parse_ping_request(&mut buf).unwrap();
+            assert_eq!(val, 42);
+        }
+
+        #[test]
+        fn parse_negative_payload() {
+            let mut buf = &b"\x09\x01\xff\xff\xff\xff\xff\xff\xff\xfe"[..];
+            let val = parse_ping_request(&mut buf).unwrap();
+            assert_eq!(val, -2);
+        }
+
+        #[test]
+        fn reject_short_payload() {
+            let mut buf = &b"\x05\x01\x00\x00\x00\x2a"[..];
+            let val = parse_ping_request(&mut buf).err().unwrap();
+            assert_eq!(val, MinecraftParseError::UnexpectedEof);
+        }
+
+        #[test]
+        fn reject_other_packet_id() {
+            let mut buf = &b"\x09\x00\x00\x00\x00\x00\x00\x00\x00\x2a"[..];
+            let val = parse_ping_request(&mut buf).err().unwrap();
+            assert_eq!(val, MinecraftParseError::UnexpectedPacketId(0x00));
+        }
+    }
+
+    mod parse_login_start {
+        use crate::*;
+
+        const UUID: u128 = 0x069a79f4_44e9_4726_a5be_fca90e38aaf5;
+
+        #[test]
+        fn parse_name_and_uuid() {
+            let mut buf = &b"\x17\x00\x05Notch\x06\x9a\x79\xf4\x44\xe9\x47\x26\xa5\xbe\xfc\xa9\x0e\x38\xaa\xf5"[..];
+            let val = parse_login_start(&mut buf, 765).unwrap();
+            assert_eq!(val.name(), "Notch");
+            assert_eq!(val.uuid(), Some(UUID));
+        }
+
+        #[test]
+        fn parse_name_only() {
+            let mut buf = &b"\x07\x00\x05Notch"[..];
+            let val = parse_login_start(&mut buf, 754).unwrap();
+            assert_eq!(val.name(), "Notch");
+            assert_eq!(val.uuid(), None);
+        }
+
+        #[test]
+        fn parse_optional_uuid_present() {
+            let mut buf = &b"\x18\x00\x05Notch\x01\x06\x9a\x79\xf4\x44\xe9\x47\x26\xa5\xbe\xfc\xa9\x0e\x38\xaa\xf5"[..];
+            let val = parse_login_start(&mut buf, 763).unwrap();
+            assert_eq!(val.uuid(), Some(UUID));
+        }
+
+        #[test]
+        fn parse_optional_uuid_absent() {
+            let mut buf = &b"\x08\x00\x05Notch\x00"[..];
+            let val = parse_login_start(&mut buf, 761).unwrap();
+            assert_eq!(val.uuid(), None);
+        }
+
+        #[test]
+        fn reject_missing_mandatory_uuid() {
+            let mut buf = &b"\x07\x00\x05Notch"[..];
+            let val = parse_login_start(&mut buf, 764).err().unwrap();
+            assert_eq!(val, MinecraftParseError::UnexpectedEof);
+        }
+
+        #[test]
+        fn reject_other_packet_id() {
+            let mut buf = &b"\x07\x01\x05Notch"[..];
+            let val = parse_login_start(&mut buf, 754).err().unwrap();
+            assert_eq!(val, MinecraftParseError::UnexpectedPacketId(0x01));
+        }
+
+        #[test]
+        fn reject_1_19_signature_layout() {
+            let mut buf = &b"\x08\x00\x05Notch\x00"[..];
+            let val = parse_login_start(&mut buf, 759).err().unwrap();
+            assert_eq!(val, MinecraftParseError::UnsupportedProtocolVersion(759));
+        }
+
+        #[test]
+        fn reject_1_19_before_reading_name() {
+            // the name is cut short, but the version alone rules the packet out
+            let mut buf = &b"\x03\x00\x05ab"[..];
+            let val = parse_login_start(&mut buf, 759).err().unwrap();
+            assert_eq!(val, MinecraftParseError::UnsupportedProtocolVersion(759));
+        }
+
+        #[test]
+        fn reject_name_over_16_chars() {
+            let mut buf = &b"\x13\x00\x11ABCDEFGHIJKLMNOPQ"[..];
+            let val = parse_login_start(&mut buf, 754).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::StringTooManyChars { max: 16 }));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    mod serde {
+        use crate::*;
+
+        #[test]
+        fn round_trip_through_json() {
+            let mut buf = test_util::SAMPLE_HANDSHAKE;
+            let handshake = parse_handshake(&mut buf).unwrap();
+            let json = serde_json::to_string(&handshake).unwrap();
+            assert_eq!(json, r#"{"protocol_version":754,"address":"123.45.67.89","port":25565,"next_state":"Login"}"#);
+            let val: Handshake = serde_json::from_str(&json).unwrap();
+            assert_eq!(val, handshake);
+        }
+
+        #[test]
+        fn reject_unknown_next_state() {
+            let json = r#"{"protocol_version":754,"address":"localhost","port":25565,"next_state":"Play"}"#;
+            assert!(serde_json::from_str::<Handshake>(json).is_err());
+        }
+    }
+
+    mod parse_encryption_request {
+        use crate::*;
+
+        #[test]
+        fn parse_without_should_authenticate() {
+            let mut buf = &b"\x0b\x01\x00\x03\x30\x81\x9f\x04\xde\xad\xbe\xef"[..];
+            let val = parse_encryption_request(&mut buf, 765).unwrap();
+            assert_eq!(val.server_id(), "");
+            assert_eq!(val.public_key(), b"\x30\x81\x9f");
+            assert_eq!(val.verify_token(), b"\xde\xad\xbe\xef");
+            assert_eq!(val.should_authenticate(), None);
+        }
+
+        #[test]
+        fn parse_with_should_authenticate() {
+            let mut buf = &b"\x0c\x01\x00\x03\x30\x81\x9f\x04\xde\xad\xbe\xef\x00"[..];
+            let val = parse_encryption_request(&mut buf, 766).unwrap();
+            assert_eq!(val.should_authenticate(), Some(false));
+        }
+
+        #[test]
+        fn reject_missing_should_authenticate() {
+            let mut buf = &b"\x0b\x01\x00\x03\x30\x81\x9f\x04\xde\xad\xbe\xef"[..];
+            let val = parse_encryption_request(&mut buf, 766).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+
+        #[test]
+        fn reject_unexpected_should_authenticate() {
+            let mut buf = &b"\x0c\x01\x00\x03\x30\x81\x9f\x04\xde\xad\xbe\xef\x01"[..];
+            let val = parse_encryption_request(&mut buf, 765).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::LengthNotMatch));
+        }
+
+        #[test]
+        fn reject_truncated_verify_token() {
+            let mut buf = &b"\x0a\x01\x00\x03\x30\x81\x9f\x04\xde\xad\xbe"[..];
+            let val = parse_encryption_request(&mut buf, 765).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::ArrayTooShort));
+        }
+    }
+
+    mod parse_encryption_response {
+        use crate::*;
+
+        #[test]
+        fn parse_verify_token() {
+            let packet = test_util::build_frame(0x01, b"\x02\xaa\xbb\x03\x01\x02\x03");
+            let mut buf = &packet[..];
+            let val = parse_encryption_response(&mut buf, 765).unwrap();
+            assert_eq!(val.shared_secret(), b"\xaa\xbb");
+            assert_eq!(val.verify_token(), Some(&b"\x01\x02\x03"[..]));
+            assert_eq!(val.salt_signature(), None);
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn parse_1_19_verify_token() {
+            let packet = test_util::build_frame(0x01, b"\x02\xaa\xbb\x01\x01\x07");
+            let val = parse_encryption_response(&mut &packet[..], 759).unwrap();
+            assert_eq!(val.verify_token(), Some(&b"\x07"[..]));
+            assert_eq!(val.salt_signature(), None);
+        }
+
+        #[test]
+        fn parse_1_19_salt_signature() {
+            let packet = test_util::build_frame(0x01, b"\x02\xaa\xbb\x00\x00\x00\x00\x00\x00\x00\x00\x2a\x02\x5a\x5b");
+            let val = parse_encryption_response(&mut &packet[..], 760).unwrap();
+            assert_eq!(val.verify_token(), None);
+            assert_eq!(val.salt_signature(), Some((42, &b"\x5a\x5b"[..])));
+        }
+
+        #[test]
+        fn no_flag_before_1_19() {
+            // the token length byte would be misread as a bool on 1.19
+            let packet = test_util::build_frame(0x01, b"\x01\xaa\x01\x07");
+            let val = parse_encryption_response(&mut &packet[..], 758).unwrap();
+            assert_eq!(val.verify_token(), Some(&b"\x07"[..]));
+        }
+
+        #[test]
+        fn reject_other_packet_id() {
+            let mut buf = &b"\x01\x00"[..];
+            let val = parse_encryption_response(&mut buf, 765).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedPacketId(0x00)));
+        }
+    }
+
+    mod parse_set_compression {
+        use crate::*;
+
+        #[test]
+        fn parse_threshold() {
+            let mut buf = &b"\x03\x03\x80\x02"[..];
+            let val = parse_set_compression(&mut buf).unwrap();
+            assert_eq!(val, 256);
+        }
+
+        #[test]
+        fn parse_disabled_threshold() {
+            let mut buf = &b"\x06\x03\xff\xff\xff\xff\x0f"[..];
+            let val = parse_set_compression(&mut buf).unwrap();
+            assert!(val < 0);
+        }
+
+        #[test]
+        fn reject_other_packet_id() {
+            let mut buf = &b"\x03\x02\x80\x02"[..];
+            let val = parse_set_compression(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedPacketId(0x02)));
+        }
+    }
+
+    mod decodable {
+        use crate::*;
+
+        #[test]
+        fn decode_handshake() {
+            let mut buf = test_util::SAMPLE_HANDSHAKE;
+            let val = decode::<Handshake>(&mut buf).unwrap();
+            assert_eq!(val, Handshake::new(754, "123.45.67.89", 25565, NextState::Login));
+        }
+
+        #[test]
+        fn decode_sequence() {
+            let mut buf = &b"\xac\x02\x01\x02hi\x63\xdd"[..];
+            assert_eq!(decode::<VarInt>(&mut buf).unwrap(), VarInt(300));
+            assert!(decode::<bool>(&mut buf).unwrap());
+            assert_eq!(decode::<String>(&mut buf).unwrap(), "hi");
+            assert_eq!(decode::<u16>(&mut buf).unwrap(), 25565);
+        }
+
+        fn round_trip<T: Decodable + Encodable + PartialEq + std::fmt::Debug>(val: T) {
+            let mut out: Vec<u8> = Vec::new();
+            val.encode(&mut out);
+            let mut buf = &out[..];
+            assert_eq!(T::decode(&mut buf).unwrap(), val);
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn round_trip_values() {
+            round_trip(Handshake::new(340, "mc.example.com", 25565, NextState::Status));
+            round_trip(VarInt(-1));
+            round_trip(VarLong(i64::MIN));
+            round_trip(-2i8);
+            round_trip(200u8);
+            round_trip(-2i16);
+            round_trip(0x1234_5678i32);
+            round_trip(i64::MAX);
+            round_trip(1.5f32);
+            round_trip(-0.25f64);
+            round_trip(u128::MAX);
+            round_trip(false);
+            round_trip("Bananrama".to_string());
+        }
+    }
+
+    mod parse_login_success {
+        use crate::*;
+
+        const UUID: &[u8] = b"\x06\x9a\x79\xf4\x44\xe9\x4b\x8a\xa4\x9d\xe0\x1b\x5a\x2e\x6a\x60";
+
+        fn packet(fields: &[u8]) -> Vec<u8> {
+            let body = [UUID, b"\x05Notch", fields].concat();
+            test_util::build_frame(0x02, &body)
+        }
+
+        #[test]
+        fn parse_without_properties() {
+            let packet = packet(b"");
+            let mut buf = &packet[..];
+            let val = parse_login_success(&mut buf, 754).unwrap();
+            assert_eq!(val.uuid(), 0x069a79f444e94b8aa49de01b5a2e6a60);
+            assert_eq!(val.username(), "Notch");
+            assert!(val.properties().is_empty());
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn parse_signed_and_unsigned_properties() {
+            let packet = packet(b"\x02\x08textures\x04e30=\x01\x03sig\x04cape\x01x\x00");
+            let mut buf = &packet[..];
+            let val = parse_login_success(&mut buf, 765).unwrap();
+            assert_eq!(val.properties(), &[
+                Property { name: "textures".to_string(), value: "e30=".to_string(), signature: Some("sig".to_string()) },
+                Property { name: "cape".to_string(), value: "x".to_string(), signature: None },
+            ]);
+        }
+
+        #[test]
+        fn parse_strict_error_handling_flag() {
+            let packet = packet(b"\x00\x01");
+            let val = parse_login_success(&mut &packet[..], 767).unwrap();
+            assert!(val.properties().is_empty());
+
+            // the flag is gone again in 1.21.2
+            let val = parse_login_success(&mut &packet[..], 768).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::LengthNotMatch));
+        }
+
+        #[test]
+        fn reject_string_uuid_versions() {
+            let packet = packet(b"");
+            let val = parse_login_success(&mut &packet[..], 578).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnsupportedProtocolVersion(578)));
+        }
+
+        #[test]
+        fn reject_truncated_property() {
+            let packet = packet(b"\x01\x08textures\x04e30=");
+            let val = parse_login_success(&mut &packet[..], 765).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+
+        #[test]
+        fn reject_other_packet_id() {
+            let mut buf = &b"\x01\x03"[..];
+            let val = parse_login_success(&mut buf, 765).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedPacketId(0x03)));
+        }
+    }
+
+    mod parse_keep_alive {
+        use crate::*;
+
+        #[test]
+        fn parse_id() {
+            let mut buf = &b"\x00\x00\x01\x8b\xcf\xe5\x68\x00"[..];
+            let val = parse_keep_alive(&mut buf).unwrap();
+            assert_eq!(val, 1700000000000);
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn reject_short_body() {
+            let mut buf = &b"\x00\x00\x01\x8b\xcf\xe5\x68"[..];
+            let val = parse_keep_alive(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+
+        #[test]
+        fn reject_long_body() {
+            let mut buf = &b"\x00\x00\x01\x8b\xcf\xe5\x68\x00\x00"[..];
+            let val = parse_keep_alive(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::LengthNotMatch));
+        }
+    }
+
+    mod parse_plugin_message {
+        use crate::*;
+
+        #[test]
+        fn parse_brand() {
+            let mut buf = &b"\x0fminecraft:brand\x07vanilla"[..];
+            let val = parse_plugin_message(&mut buf).unwrap();
+            assert_eq!(val.channel().to_string(), "minecraft:brand");
+            assert_eq!(val.data(), b"\x07vanilla");
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn parse_empty_data() {
+            let mut buf = &b"\x0cbungeecord:x"[..];
+            let val = parse_plugin_message(&mut buf).unwrap();
+            assert_eq!(val.channel().namespace(), "bungeecord");
+            assert!(val.data().is_empty());
+        }
+
+        #[test]
+        fn data_stops_at_frame_end() {
+            let mut buf = &b"\x0a\x00\x06fml:hs\x01\x02\x03\x04"[..];
+            let val = parse_frame(&mut buf, |_, body| parse_plugin_message(body)).unwrap();
+            assert_eq!(val.data(), b"\x01\x02");
+            assert_eq!(buf, b"\x03\x04");
+        }
+
+        #[test]
+        fn reject_invalid_channel() {
+            let mut buf = &b"\x05MC|Br\x00"[..];
+            let val = parse_plugin_message(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::InvalidIdentifier(_)));
+        }
+    }
+
+    mod parse_client_information {
+        use crate::*;
+
+        #[test]
+        fn parse_fields_in_order() {
+            let mut buf = &b"\x05en_us\x0c\x01\x01\x7f\x00\x00\x01"[..];
+            let val = parse_client_information(&mut buf, 767).unwrap();
+            assert_eq!(val.locale(), "en_us");
+            assert_eq!(val.view_distance(), 12);
+            assert_eq!(val.chat_mode(), 1);
+            assert!(val.chat_colors());
+            assert_eq!(val.skin_parts(), 0x7f);
+            assert_eq!(val.main_hand(), 0);
+            assert!(!val.text_filtering());
+            assert!(val.allow_listing());
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn drop_particle_status_from_1_21_2() {
+            let mut buf = &b"\x05ja_jp\x02\x00\x00\x00\x01\x01\x00\x02"[..];
+            let val = parse_client_information(&mut buf, 768).unwrap();
+            assert_eq!(val.locale(), "ja_jp");
+            assert_eq!(val.main_hand(), 1);
+            assert!(val.text_filtering());
+            assert!(!val.allow_listing());
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn reject_missing_allow_listing() {
+            let mut buf = &b"\x05en_us\x0c\x01\x01\x7f\x00\x00"[..];
+            let val = parse_client_information(&mut buf, 767).err().unwrap();
+            assert_eq!(val, MinecraftParseError::UnexpectedEof);
+        }
+
+        #[test]
+        fn reject_before_1_18() {
+            let mut buf = &b"\x05en_us\x0c\x01\x01\x7f\x00\x00"[..];
+            let val = parse_client_information(&mut buf, 756).err().unwrap();
+            assert_eq!(val, MinecraftParseError::UnsupportedProtocolVersion(756));
+        }
+    }
+
+    mod parse_player_position {
+        use crate::*;
+
+        #[test]
+        fn parse_fields_in_order() {
+            let mut buf = &b"\x40\x59\x00\x00\x00\x00\x00\x00\x40\x50\x40\x00\x00\x00\x00\x00\xc0\x24\x00\x00\x00\x00\x00\x00\x01"[..];
+            let val = parse_player_position(&mut buf).unwrap();
+            assert_eq!(val.x(), 100.0);
+            assert_eq!(val.y(), 65.0);
+            assert_eq!(val.z(), -10.0);
+            assert!(val.on_ground());
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn parse_in_air() {
+            let mut buf = &[&[0u8; 24][..], b"\x00"].concat()[..];
+            assert!(!parse_player_position(&mut buf).unwrap().on_ground());
+        }
+
+        #[test]
+        fn reject_non_bool_on_ground() {
+            let mut buf = &[&[0u8; 24][..], b"\x02"].concat()[..];
+            let val = parse_player_position(&mut buf).err().unwrap();
+            assert_eq!(val, MinecraftParseError::InvalidBool(0x02));
+        }
+
+        #[test]
+        fn reject_missing_on_ground() {
+            let mut buf = &[0u8; 24][..];
+            let val = parse_player_position(&mut buf).err().unwrap();
+            assert_eq!(val, MinecraftParseError::UnexpectedEof);
+        }
+    }
+
+    mod parse_player_position_rotation {
+        use crate::*;
+
+        #[test]
+        fn parse_fields_in_order() {
+            let mut buf = &b"\x40\x59\x00\x00\x00\x00\x00\x00\x40\x50\x40\x00\x00\x00\x00\x00\xc0\x24\x00\x00\x00\x00\x00\x00\x43\x34\x00\x00\xc2\xb4\x00\x00\x00"[..];
+            let val = parse_player_position_rotation(&mut buf).unwrap();
+            assert_eq!(val.x(), 100.0);
+            assert_eq!(val.y(), 65.0);
+            assert_eq!(val.z(), -10.0);
+            assert_eq!(val.yaw(), 180.0);
+            assert_eq!(val.pitch(), -90.0);
+            assert!(!val.on_ground());
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn reject_non_bool_on_ground() {
+            let mut buf = &[&[0u8; 32][..], b"\xff"].concat()[..];
+            let val = parse_player_position_rotation(&mut buf).err().unwrap();
+            assert_eq!(val, MinecraftParseError::InvalidBool(0xff));
+        }
+    }
+
+    mod parse_slot {
+        use crate::*;
+
+        #[test]
+        fn parse_empty_slot() {
+            let mut buf = &b"\x00"[..];
+            let val = parse_slot(&mut buf, 765).unwrap();
+            assert!(val.is_empty());
+            assert_eq!(val.item_id(), None);
+            assert_eq!(val.count(), 0);
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn parse_stone_stack() {
+            let mut buf = &b"\x01\x01\x40\x00"[..];
+            let val = parse_slot(&mut buf, 765).unwrap();
+            assert_eq!(val.item_id(), Some(1));
+            assert_eq!(val.count(), 64);
+            assert_eq!(val.nbt(), None);
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn parse_named_nbt_before_1_20_2() {
+            let mut buf = &b"\x01\x01\x01\x0a\x00\x00\x03\x00\x06Damage\x00\x00\x00\x05\x00"[..];
+            let val = parse_slot(&mut buf, 763).unwrap();
+            assert_eq!(val.nbt(), Some(&NbtTag::Compound(vec![("Damage".to_string(), NbtTag::Int(5))])));
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn parse_network_nbt_from_1_20_2() {
+            let mut buf = &b"\x01\x01\x01\x0a\x03\x00\x06Damage\x00\x00\x00\x05\x00"[..];
+            let val = parse_slot(&mut buf, 764).unwrap();
+            assert_eq!(val.nbt(), Some(&NbtTag::Compound(vec![("Damage".to_string(), NbtTag::Int(5))])));
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn reject_component_format() {
+            let mut buf = &b"\x01\x01\x40\x00\x00"[..];
+            let val = parse_slot(&mut buf, 766).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnsupportedProtocolVersion(766)));
+        }
+    }
+
+    mod parse_known_packs {
+        use crate::*;
+
+        #[test]
+        fn parse_empty() {
+            let mut buf = &b"\x00"[..];
+            let val = parse_known_packs(&mut buf).unwrap();
+            assert!(val.is_empty());
+        }
+
+        #[test]
+        fn parse_two_packs() {
+            let mut buf = &b"\x02\x09minecraft\x04core\x061.21.1\x06mypack\x05extra\x031.0"[..];
+            let val = parse_known_packs(&mut buf).unwrap();
+            assert_eq!(val.len(), 2);
+            assert_eq!(val[0].namespace(), "minecraft");
+            assert_eq!(val[0].id(), "core");
+            assert_eq!(val[0].version(), "1.21.1");
+            assert_eq!(val[1].namespace(), "mypack");
+            assert_eq!(val[1].id(), "extra");
+            assert_eq!(val[1].version(), "1.0");
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn reject_missing_entry() {
+            let mut buf = &b"\x02\x09minecraft\x04core\x061.21.1"[..];
+            let val = parse_known_packs(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+    }
+
+    mod parse_cookie_request {
+        use crate::*;
+
+        #[test]
+        fn parse_key() {
+            let mut buf = &b"\x0emyproxy:ticket"[..];
+            let val = parse_cookie_request(&mut buf).unwrap();
+            assert_eq!(val.key().to_string(), "myproxy:ticket");
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn reject_invalid_key() {
+            let mut buf = &b"\x03A:B"[..];
+            let val = parse_cookie_request(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::InvalidIdentifier(_)));
+        }
+    }
+
+    mod parse_cookie_response {
+        use crate::*;
+
+        #[test]
+        fn parse_payload() {
+            let mut buf = &b"\x06ticket\x01\x03\x01\x02\x03"[..];
+            let val = parse_cookie_response(&mut buf).unwrap();
+            assert_eq!(val.key().to_string(), "minecraft:ticket");
+            assert_eq!(val.payload(), Some(&b"\x01\x02\x03"[..]));
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn parse_missing_cookie() {
+            let mut buf = &b"\x06ticket\x00"[..];
+            let val = parse_cookie_response(&mut buf).unwrap();
+            assert_eq!(val.payload(), None);
+        }
+
+        #[test]
+        fn accept_payload_at_cap() {
+            let mut packet = b"\x06ticket\x01\x80\x28".to_vec();
+            packet.extend_from_slice(&[0xab; 5120]);
+            let val = parse_cookie_response(&mut &packet[..]).unwrap();
+            assert_eq!(val.payload().unwrap().len(), 5120);
+        }
+
+        #[test]
+        fn reject_payload_over_cap_before_reading() {
+            let mut buf = &b"\x06ticket\x01\x81\x28"[..];
+            let val = parse_cookie_response(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::CookieTooLarge(5121)));
+        }
+
+        #[test]
+        fn reject_truncated_payload() {
+            let mut buf = &b"\x06ticket\x01\x03\x01"[..];
+            let val = parse_cookie_response(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::ArrayTooShort));
+        }
+    }
+
+    mod parse_chat_message {
+        use crate::*;
+
+        fn header(message: &str) -> Vec<u8> {
+            let mut out = Vec::new();
+            encode_string_n(message, &mut out);
+            out.extend_from_slice(&1700000000000i64.to_be_bytes());
+            out.extend_from_slice(&(-42i64).to_be_bytes());
+            out
+        }
+
+        #[test]
+        fn parse_1_19_signed() {
+            let mut packet = header("hello");
+            packet.extend_from_slice(b"\x03\x0a\x0b\x0c\x00");
+            let mut buf = &packet[..];
+            let val = parse_chat_message(&mut buf, 759).unwrap();
+            assert_eq!(val.message(), "hello");
+            assert_eq!(val.timestamp(), 1700000000000);
+            assert_eq!(val.salt(), -42);
+            assert_eq!(val.signature(), Some(&b"\x0a\x0b\x0c"[..]));
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn parse_1_19_unsigned() {
+            let mut packet = header("hello");
+            packet.extend_from_slice(b"\x00\x00");
+            let val = parse_chat_message(&mut &packet[..], 759).unwrap();
+            assert_eq!(val.signature(), None);
+        }
+
+        #[test]
+        fn parse_1_19_1_last_seen() {
+            let mut packet = header("hi");
+            packet.extend_from_slice(b"\x01\x0a\x00\x01");
+            packet.extend_from_slice(&[0x11; 16]);
+            packet.extend_from_slice(b"\x01\x0b\x01");
+            packet.extend_from_slice(&[0x22; 16]);
+            packet.extend_from_slice(b"\x01\x0c");
+            let mut buf = &packet[..];
+            let val = parse_chat_message(&mut buf, 760).unwrap();
+            assert_eq!(val.signature(), Some(&b"\x0a"[..]));
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn parse_1_20_signed() {
+            let mut packet = header("gg");
+            packet.push(0x01);
+            packet.extend_from_slice(&[0x5a; 256]);
+            packet.extend_from_slice(b"\x02\xff\xff\x0f");
+            let mut buf = &packet[..];
+            let val = parse_chat_message(&mut buf, 763).unwrap();
+            assert_eq!(val.message(), "gg");
+            assert_eq!(val.signature(), Some(&[0x5a; 256][..]));
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn parse_1_20_unsigned() {
+            let mut packet = header("gg");
+            packet.extend_from_slice(b"\x00\x00\x00\x00\x00");
+            let mut buf = &packet[..];
+            let val = parse_chat_message(&mut buf, 765).unwrap();
+            assert_eq!(val.signature(), None);
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn parse_1_21_5_checksum() {
+            let mut packet = header("gg");
+            packet.extend_from_slice(b"\x00\x00\x00\x00\x00\x7f");
+            let mut buf = &packet[..];
+            parse_chat_message(&mut buf, 770).unwrap();
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn reject_truncated_signature() {
+            let mut packet = header("gg");
+            packet.push(0x01);
+            packet.extend_from_slice(&[0x5a; 255]);
+            let val = parse_chat_message(&mut &packet[..], 765).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+
+        #[test]
+        fn reject_unsigned_chat_versions() {
+            let packet = header("hello");
+            let val = parse_chat_message(&mut &packet[..], 758).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnsupportedProtocolVersion(758)));
+        }
+    }
+
+    mod parse_login_plugin_request {
+        use crate::*;
+
+        #[test]
+        fn parse_request() {
+            let mut buf = &b"\x15\x04\x07\x10fml:loginwrapper\x01\x02"[..];
+            let val = parse_login_plugin_request(&mut buf).unwrap();
+            assert_eq!(val.message_id(), 7);
+            assert_eq!(val.channel().to_string(), "fml:loginwrapper");
+            assert_eq!(val.data(), b"\x01\x02");
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn data_stops_at_frame_end() {
+            let mut buf = &b"\x0a\x04\x00\x06fml:hs\x01\x02\x03"[..];
+            let val = parse_login_plugin_request(&mut buf).unwrap();
+            assert_eq!(val.data(), b"\x01");
+            assert_eq!(buf, b"\x02\x03");
+        }
+
+        #[test]
+        fn reject_other_packet_id() {
+            let mut buf = &b"\x03\x02\x00\x00"[..];
+            let val = parse_login_plugin_request(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedPacketId(0x02)));
+        }
+    }
 
-#[derive(Error, Debug, PartialEq)]
-pub enum MinecraftParseError {
-    #[error("VarInt is corrupted")]
-    InvalidVarInt,
-    #[error("u_short is corrupted")]
-    InvalidUShort,
-    #[error("Byte-encoded string is corrupted")]
-    InvalidStringEncoding(#[from] str::Utf8Error),
-    #[error("Byte-encoded string length is not sufficient")]
-    StringTooShort,
-    #[error("Packet length does not match its actual payload")]
-    LengthNotMatch,
-    #[error("This packet is not for handshaking")]
-    NotHandshake,
-}
+    mod parse_login_plugin_response {
+        use crate::*;
 
+        #[test]
+        fn parse_successful() {
+            let mut buf = &b"\x05\x02\x07\x01\xab\xcd"[..];
+            let val = parse_login_plugin_response(&mut buf).unwrap();
+            assert_eq!(val.message_id(), 7);
+            assert_eq!(val.data(), Some(&b"\xab\xcd"[..]));
+        }
 
-#[derive(Debug, PartialEq)]
-pub struct Handshake {
-    protocol_version: i32,
-    address: String,
-    port: u16,
-    next_state: i32,
-}
+        #[test]
+        fn parse_successful_without_data() {
+            let mut buf = &b"\x03\x02\x07\x01"[..];
+            let val = parse_login_plugin_response(&mut buf).unwrap();
+            assert_eq!(val.data(), Some(&b""[..]));
+        }
 
-fn parse_ushort(buf: &mut dyn Buf) -> Result<u16, MinecraftParseError> {
-    if buf.remaining() < 2 {
-        return Err(MinecraftParseError::InvalidUShort);
+        #[test]
+        fn parse_not_understood() {
+            let mut buf = &b"\x03\x02\x07\x00"[..];
+            let val = parse_login_plugin_response(&mut buf).unwrap();
+            assert_eq!(val.data(), None);
+        }
+
+        #[test]
+        fn reject_data_when_not_understood() {
+            let mut buf = &b"\x04\x02\x07\x00\xab"[..];
+            let val = parse_login_plugin_response(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::LengthNotMatch));
+        }
     }
-    let val = buf.get_u16();
-    Ok(val)
-}
 
-fn parse_varint(buf: &mut dyn Buf) -> Result<i32, MinecraftParseError> {
-    let mut v: i32 = 0;
-    let mut bit_place: usize = 0;
-    let mut i: usize = 0;
-    let mut has_more = true;
+    mod parse_login_disconnect {
+        use crate::*;
 
-    while has_more {
-        if i == VARINT_MAX_BYTES || buf.remaining() < 1 {
-            return Err(MinecraftParseError::InvalidVarInt)
+        #[test]
+        fn parse_reason() {
+            let mut buf = &b"\x1a\x00\x18{\"text\":\"Server closed\"}"[..];
+            let val = parse_login_disconnect(&mut buf).unwrap();
+            assert_eq!(val, r#"{"text":"Server closed"}"#);
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn reject_other_packet_id() {
+            let mut buf = &b"\x04\x02\x02\"\""[..];
+            let val = parse_login_disconnect(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedPacketId(0x02)));
+        }
+
+        #[test]
+        fn reject_truncated_reason() {
+            let mut buf = &b"\x03\x00\x05{"[..];
+            let val = parse_login_disconnect(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::StringTooShort));
         }
-        let byte = buf.get_u8();
-        
-        has_more = byte & 0x80 != 0;
-        v |= ((byte as i32) & 0x7F) << bit_place;
-        bit_place += 7;
-        i += 1;
     }
 
-    Ok(v)
-}
+    mod detect_protocol {
+        use crate::*;
+
+        #[test]
+        fn detect_modern() {
+            assert_eq!(detect_protocol(b"\x13\x00\xf2\x05"), Some(ProtocolFlavor::Modern));
+            // a handshake longer than 127 bytes starts with a continuation byte
+            assert_eq!(detect_protocol(b"\x80\x01\x00"), Some(ProtocolFlavor::Modern));
+        }
 
-fn parse_string_n(buf: &mut dyn Buf) -> Result<String, MinecraftParseError> {
-    let len = parse_varint(buf)? as usize;
+        #[test]
+        fn detect_legacy_1_6() {
+            assert_eq!(detect_protocol(b"\xfe\x01\xfa\x00\x0b"), Some(ProtocolFlavor::Legacy16));
+        }
 
-    if buf.remaining() < len {
-        return Err(MinecraftParseError::StringTooShort);
-    }
+        #[test]
+        fn detect_legacy_1_4() {
+            assert_eq!(detect_protocol(b"\xfe\x01\x00"), Some(ProtocolFlavor::Legacy14));
+            assert_eq!(detect_protocol(b"\xfe\x00"), Some(ProtocolFlavor::Legacy14));
+        }
 
-    let bytes = buf.copy_to_bytes(len);
-    let val: String = str::from_utf8(&bytes)?.to_string();
-    Ok(val)
-}
+        #[test]
+        fn wait_for_first_byte() {
+            assert_eq!(detect_protocol(b""), None);
+        }
 
-pub fn parse_handshake(buf: &mut dyn Buf) -> Result<Handshake, MinecraftParseError> {
-    let len = parse_varint(buf)?;
-    if buf.remaining() != len as usize {
-        return Err(MinecraftParseError::LengthNotMatch);
+        #[test]
+        fn wait_on_legacy_prefixes() {
+            // either may be all an old client sends, or the start of a 1.6 ping
+            assert_eq!(detect_protocol(b"\xfe"), None);
+            assert_eq!(detect_protocol(b"\xfe\x01"), None);
+        }
     }
 
-    let id = parse_varint(buf)?;
-    if id != 0x00 {
-        return Err(MinecraftParseError::NotHandshake);
+    mod parse_legacy_ping {
+        use crate::*;
+
+        fn utf16(s: &str) -> Vec<u8> {
+            let units: Vec<u16> = s.encode_utf16().collect();
+            let mut out = (units.len() as u16).to_be_bytes().to_vec();
+            for unit in units {
+                out.extend_from_slice(&unit.to_be_bytes());
+            }
+            out
+        }
+
+        fn legacy_ping(hostname: &str, port: i32) -> Vec<u8> {
+            let mut data = vec![74];
+            data.extend(utf16(hostname));
+            data.extend_from_slice(&port.to_be_bytes());
+
+            let mut packet = b"\xfe\x01\xfa".to_vec();
+            packet.extend(utf16("MC|PingHost"));
+            packet.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            packet.extend(data);
+            packet
+        }
+
+        #[test]
+        fn parse_good_packet() {
+            let mut buf = &b"\xfe\x01\xfa\x00\x0b\x00\x4d\x00\x43\x00\x7c\x00\x50\x00\x69\x00\x6e\x00\x67\x00\x48\x00\x6f\x00\x73\x00\x74\x00\x19\x4a\x00\x09\x00\x6c\x00\x6f\x00\x63\x00\x61\x00\x6c\x00\x68\x00\x6f\x00\x73\x00\x74\x00\x00\x63\xdd"[..];
+            let val = parse_legacy_ping(&mut buf).unwrap();
+            assert_eq!(val.protocol_version(), 74);
+            assert_eq!(val.hostname(), "localhost");
+            assert_eq!(val.port(), 25565);
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn parse_non_ascii_hostname() {
+            let packet = legacy_ping("m\u{fc}nchen.\u{1f600}.example", 25566);
+            let val = parse_legacy_ping(&mut &packet[..]).unwrap();
+            assert_eq!(val.hostname(), "m\u{fc}nchen.\u{1f600}.example");
+            assert_eq!(val.port(), 25566);
+        }
+
+        #[test]
+        fn reject_modern_packet() {
+            let mut buf = &b"\x13\x00\xf2\x05"[..];
+            let val = parse_legacy_ping(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::NotLegacyPing));
+        }
+
+        #[test]
+        fn reject_other_channel() {
+            let mut packet = b"\xfe\x01\xfa".to_vec();
+            packet.extend(utf16("MC|Brand"));
+            let val = parse_legacy_ping(&mut &packet[..]).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::NotLegacyPing));
+        }
+
+        #[test]
+        fn reject_unpaired_surrogate() {
+            let mut packet = legacy_ping("ab", 25565);
+            // replace the hostname's first code unit with a lone high surrogate
+            let hostname_start = packet.len() - 4 - 4;
+            packet[hostname_start] = 0xd8;
+            let val = parse_legacy_ping(&mut &packet[..]).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::InvalidUtf16String));
+        }
+
+        #[test]
+        fn reject_data_length_mismatch() {
+            // grow the declared data length by one stray trailing byte
+            let mut packet = legacy_ping("localhost", 25565);
+            packet.push(0x00);
+            let data_len_at = 3 + 2 + 22;
+            packet[data_len_at + 1] += 1;
+            let val = parse_legacy_ping(&mut &packet[..]).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::LengthNotMatch));
+        }
+
+        #[test]
+        fn reject_truncated_packet() {
+            let packet = legacy_ping("localhost", 25565);
+            let val = parse_legacy_ping(&mut &packet[..packet.len() - 1]).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::LengthNotMatch));
+        }
     }
 
-    let version = parse_varint(buf)?;
-    let address = parse_string_n(buf)?;
-    let port = parse_ushort(buf)?;
-    let next_state = parse_varint(buf)?;
+    mod parse_packet {
+        use crate::*;
 
-    let handshake = Handshake {
-        protocol_version: version,
-        address,
-        port,
-        next_state
-    };
+        #[test]
+        fn dispatch_handshake() {
+            let mut buf = &b"\x13\x00\xf2\x05\x0c\x31\x32\x33\x2e\x34\x35\x2e\x36\x37\x2e\x38\x39\x63\xdd\x01"[..];
+            let val = parse_packet(ConnectionState::Handshaking, &mut buf).unwrap();
+            assert!(matches!(val, Packet::Handshake(ref h) if h.next_state() == NextState::Status));
+        }
 
-    Ok(handshake)
-}
+        #[test]
+        fn dispatch_status_request() {
+            let mut buf = &b"\x01\x00"[..];
+            let val = parse_packet(ConnectionState::Status, &mut buf).unwrap();
+            assert_eq!(val, Packet::StatusRequest);
+        }
 
+        #[test]
+        fn dispatch_ping_request() {
+            let mut buf = &b"\x09\x01\x00\x00\x00\x00\x00\x00\x00\x2a"[..];
+            let val = parse_packet(ConnectionState::Status, &mut buf).unwrap();
+            assert_eq!(val, Packet::PingRequest(42));
+        }
 
-#[cfg(test)]
-mod tests {
-    mod parse_varint {
+        #[test]
+        fn dispatch_login_start() {
+            let mut buf = &b"\x07\x00\x05Notch"[..];
+            let val = parse_packet(ConnectionState::Login { protocol_version: 754 }, &mut buf).unwrap();
+            assert!(matches!(val, Packet::LoginStart(ref l) if l.name() == "Notch"));
+        }
+
+        #[test]
+        fn reject_unknown_id_in_state() {
+            let mut buf = &b"\x01\x01"[..];
+            let val = parse_packet(ConnectionState::Login { protocol_version: 754 }, &mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedPacketId(0x01)));
+        }
+
+        #[test]
+        fn reject_non_handshake_while_handshaking() {
+            let mut buf = &b"\x01\x01"[..];
+            let val = parse_packet(ConnectionState::Handshaking, &mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::NotHandshake));
+        }
+    }
+
+    mod forge_marker {
         use crate::*;
 
+        fn handshake(address: &str) -> Handshake {
+            Handshake::new(340, address, 25565, NextState::Login)
+        }
+
         #[test]
-        fn parse_1_byte_positive_value() {
-            let mut buf = &b"\x16"[..];
-            let val = parse_varint(&mut buf).unwrap();
-            assert_eq!(val, 22);
+        fn detect_fml() {
+            let val = handshake("mc.example.com\0FML\0");
+            assert_eq!(val.forge_marker(), Some(ForgeMarker::Fml));
+            assert_eq!(val.clean_address(), "mc.example.com");
         }
+
         #[test]
-        fn parse_0() {
-            let mut buf = &b"\x00"[..];
-            let val = parse_varint(&mut buf).unwrap();
-            assert_eq!(val, 0);
+        fn detect_fml2() {
+            let val = handshake("mc.example.com\0FML2\0");
+            assert_eq!(val.forge_marker(), Some(ForgeMarker::Fml2));
+            assert_eq!(val.clean_address(), "mc.example.com");
         }
 
         #[test]
-        fn parse_754() {
-            let mut buf = &b"\xf2\x05"[..];
-            let val = parse_varint(&mut buf).unwrap();
-            assert_eq!(val, 754);
+        fn detect_forge_with_extra_data() {
+            let val = handshake("mc.example.com\0FORGE3");
+            assert_eq!(val.forge_marker(), Some(ForgeMarker::Forge));
+            assert_eq!(val.clean_address(), "mc.example.com");
         }
 
         #[test]
-        fn reject_empty_packet() {
-            let mut buf = &b""[..];
-            let val = parse_handshake(&mut buf).err().unwrap();
-            assert!(matches!(val, MinecraftParseError::InvalidVarInt));
+        fn vanilla_address() {
+            let val = handshake("mc.example.com");
+            assert_eq!(val.forge_marker(), None);
+            assert_eq!(val.clean_address(), "mc.example.com");
         }
 
         #[test]
-        fn reject_not_terminated_packet() {
-            let mut buf = &b"\xd7"[..];
-            let val = parse_handshake(&mut buf).err().unwrap();
-            assert!(matches!(val, MinecraftParseError::InvalidVarInt));
+        fn unknown_suffix_is_stripped_but_not_a_marker() {
+            let val = handshake("mc.example.com\x00192.168.0.1\0");
+            assert_eq!(val.forge_marker(), None);
+            assert_eq!(val.clean_address(), "mc.example.com");
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    mod tracing_events {
+        use crate::*;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use tracing::{span, Event, Level, Metadata, Subscriber};
+
+        struct WarnCounter(Arc<AtomicUsize>);
+
+        impl Subscriber for WarnCounter {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+                span::Id::from_u64(1)
+            }
+            fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+            fn event(&self, event: &Event<'_>) {
+                if *event.metadata().level() == Level::WARN {
+                    self.0.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+            fn enter(&self, _: &span::Id) {}
+            fn exit(&self, _: &span::Id) {}
         }
+
+        fn count_warnings(f: impl FnOnce()) -> usize {
+            let count = Arc::new(AtomicUsize::new(0));
+            tracing::subscriber::with_default(WarnCounter(count.clone()), f);
+            count.load(Ordering::SeqCst)
+        }
+
         #[test]
-        fn reject_too_long_num() {
-            let mut buf = &b"\xf3\xf3\xf3\xf3\xf3\x05"[..];
-            let val = parse_varint(&mut buf).err().unwrap();
-            assert!(matches!(val, MinecraftParseError::InvalidVarInt));
+        fn warn_on_rejected_handshake() {
+            let warnings = count_warnings(|| {
+                parse_handshake(&mut &b"\x01\x01"[..]).unwrap_err();
+            });
+            assert!(warnings > 0);
+        }
+
+        #[test]
+        fn quiet_on_good_handshake() {
+            let warnings = count_warnings(|| {
+                parse_handshake(&mut &test_util::SAMPLE_HANDSHAKE[..]).unwrap();
+            });
+            assert_eq!(warnings, 0);
         }
     }
 
-    mod parse_ushort {
+    mod derives {
         use crate::*;
+        use std::collections::HashSet;
 
         #[test]
-        fn parse_25565() {
-            let mut buf = &b"\x63\xdd"[..];
-            let val = parse_ushort(&mut buf).unwrap();
-            assert_eq!(val, 25565);
+        fn dedup_handshakes() {
+            let handshake = Handshake::new(754, "123.45.67.89", 25565, NextState::Login);
+            let mut seen = HashSet::new();
+            assert!(seen.insert(handshake.clone()));
+            assert!(!seen.insert(handshake));
+            assert!(seen.insert(Handshake::new(754, "123.45.67.89", 25565, NextState::Status)));
         }
+    }
+
+    mod display {
+        use crate::*;
 
         #[test]
-        fn reject_1_byte() {
-            let mut buf = &b"\x63"[..];
-            let val = parse_ushort(&mut buf).err().unwrap();
-            assert_eq!(val, MinecraftParseError::InvalidUShort);
+        fn display_handshake() {
+            let val = Handshake::new(754, "123.45.67.89", 25565, NextState::Login);
+            assert_eq!(val.to_string(), "Handshake(protocol=754, addr=123.45.67.89:25565, next=Login)");
         }
 
         #[test]
-        fn reject_empty() {
-            let mut buf = &b""[..];
-            let val = parse_ushort(&mut buf).err().unwrap();
-            assert_eq!(val, MinecraftParseError::InvalidUShort);
+        fn display_next_state() {
+            assert_eq!(NextState::Status.to_string(), "Status");
+            assert_eq!(NextState::Transfer.to_string(), "Transfer");
         }
     }
 
-    mod parse_string_n {
+    mod handshake_try_from {
         use crate::*;
 
         #[test]
-        fn parse_string() {
-            let mut buf = &b"\x0c\x31\x32\x33\x2e\x34\x35\x2e\x36\x37\x2e\x38\x39"[..];
-            let val = parse_string_n(&mut buf).unwrap();
-            assert_eq!(val, "123.45.67.89".to_string());
+        fn convert_sample() {
+            let packet = test_util::SAMPLE_HANDSHAKE;
+            let val = Handshake::try_from(packet).unwrap();
+            assert_eq!(val.protocol_version(), 754);
+            assert_eq!(val.address(), "123.45.67.89");
+            assert_eq!(val.port(), 25565);
+            assert_eq!(val.next_state(), NextState::Login);
         }
-        
+
         #[test]
-        fn reject_invalid_string() {
-            let mut buf = &b"\x0f\xf1\x36\x30\x2e\x32\x35\x31\x2e\x31\x30\x30\x2e\x32\x34\x39"[..];
-            let val = parse_string_n(&mut buf).err().unwrap();
-            assert!(matches!(val, MinecraftParseError::InvalidStringEncoding(_)));
+        fn reject_malformed() {
+            let val = Handshake::try_from(&b"\x13\x00\xf2\x05\x0c123"[..]).err().unwrap();
+            assert_eq!(val, MinecraftParseError::LengthNotMatch);
         }
-        
+    }
+
+    mod handshake_builder {
+        use crate::*;
+
         #[test]
-        fn reject_short_string() {
-            let mut buf = &b"\x03\x31\x36"[..];
-            let val = parse_string_n(&mut buf).err().unwrap();
-            assert!(matches!(val, MinecraftParseError::StringTooShort));
+        fn build_with_defaults() {
+            let val = Handshake::builder().build();
+            assert_eq!(val, Handshake::new(754, "localhost", 25565, NextState::Status));
         }
 
         #[test]
-        fn reject_empty() {
-            let mut buf = &b""[..];
-            let val = parse_string_n(&mut buf).err().unwrap();
-            assert!(matches!(val, MinecraftParseError::InvalidVarInt));
+        fn build_and_encode() {
+            let val = Handshake::builder()
+                .protocol_version(754)
+                .address("123.45.67.89")
+                .port(25565)
+                .next_state(NextState::Login)
+                .build();
+            let mut out = Vec::new();
+            encode_handshake(&val, &mut out);
+            assert_eq!(out, test_util::SAMPLE_HANDSHAKE);
+        }
+
+        #[test]
+        fn new_accepts_owned_address() {
+            let val = Handshake::new(47, String::from("mc.example.com"), 25566, NextState::Login);
+            assert_eq!(val.protocol_version(), 47);
+            assert_eq!(val.address(), "mc.example.com");
+            assert_eq!(val.port(), 25566);
+        }
+
+        #[test]
+        fn into_parts_of_sample() {
+            let val = parse_handshake(&mut &test_util::SAMPLE_HANDSHAKE[..]).unwrap();
+            let (protocol_version, address, port, next_state) = val.into_parts();
+            assert_eq!(protocol_version, 754);
+            assert_eq!(address, "123.45.67.89");
+            assert_eq!(port, 25565);
+            assert_eq!(next_state, NextState::Login);
+        }
+
+        #[test]
+        fn handshake_default() {
+            let val = Handshake::default();
+            assert_eq!(val, Handshake::new(754, "", 25565, NextState::Status));
+            assert_eq!(i32::from(val.next_state()), 1);
         }
     }
 
-    mod parse_handshake {
+    mod packet_builder {
         use crate::*;
 
         #[test]
-        fn parse_good_packet() {
-            let mut buf = &b"\x13\x00\xf2\x05\x0c\x31\x32\x33\x2e\x34\x35\x2e\x36\x37\x2e\x38\x39\x63\xdd\x02"[..];
-            let val = parse_handshake(&mut buf).unwrap();
-            assert_eq!(val, Handshake {
-                protocol_version: 754,
-                address: "123.45.67.89".to_string(),
-                port: 25565,
-                next_state: 2,
-            });
+        fn build_sample_handshake() {
+            let packet = PacketBuilder::new(0x00).varint(754).string("123.45.67.89").ushort(25565).varint(2).build();
+            assert_eq!(packet, test_util::SAMPLE_HANDSHAKE);
+        }
+
+        #[test]
+        fn build_id_only() {
+            assert_eq!(PacketBuilder::new(0x00).build(), b"\x01\x00");
+        }
+
+        #[test]
+        fn append_raw_bytes() {
+            let packet = PacketBuilder::new(0x03).bytes(b"\x80\x02").build();
+            assert_eq!(parse_set_compression(&mut &packet[..]).unwrap(), 256);
+        }
+    }
+
+    mod encode_handshake {
+        use crate::*;
+
+        #[test]
+        fn round_trip_good_packet() {
+            let packet = test_util::SAMPLE_HANDSHAKE;
+            let handshake = parse_handshake(&mut &packet[..]).unwrap();
+            let mut out = Vec::new();
+            encode_handshake(&handshake, &mut out);
+            assert_eq!(out, packet);
+        }
+
+        #[test]
+        fn to_bytes_reproduces_sample() {
+            let packet = test_util::SAMPLE_HANDSHAKE;
+            let handshake = parse_handshake(&mut &packet[..]).unwrap();
+            assert_eq!(handshake.to_bytes(), packet);
+        }
+
+        #[test]
+        fn wire_len_matches_sample() {
+            let packet = test_util::SAMPLE_HANDSHAKE;
+            let handshake = parse_handshake(&mut &packet[..]).unwrap();
+            assert_eq!(handshake.wire_len(), 0x13);
+            assert_eq!(handshake.total_len(), packet.len());
+        }
+
+        #[test]
+        fn total_len_with_two_byte_prefix() {
+            let handshake = Handshake { address: "a".repeat(200), ..Default::default() };
+            assert_eq!(handshake.total_len(), handshake.to_bytes().len());
+            assert_eq!(handshake.total_len(), handshake.wire_len() + 2);
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,368 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use bytes::Buf;
+use core::str;
+
+use crate::{
+    parse_byte, parse_double, parse_float, parse_int, parse_long, parse_short, parse_ubyte, parse_ushort, MinecraftParseError,
+};
+
+const TAG_END: u8 = 0;
+const TAG_BYTE: u8 = 1;
+const TAG_SHORT: u8 = 2;
+const TAG_INT: u8 = 3;
+const TAG_LONG: u8 = 4;
+const TAG_FLOAT: u8 = 5;
+const TAG_DOUBLE: u8 = 6;
+const TAG_BYTE_ARRAY: u8 = 7;
+const TAG_STRING: u8 = 8;
+const TAG_LIST: u8 = 9;
+const TAG_COMPOUND: u8 = 10;
+const TAG_INT_ARRAY: u8 = 11;
+const TAG_LONG_ARRAY: u8 = 12;
+
+// same nesting limit as the vanilla reader
+const MAX_DEPTH: usize = 512;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NbtTag {
+    End,
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<u8>),
+    String(String),
+    List(Vec<NbtTag>),
+    Compound(Vec<(String, NbtTag)>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+/// Parses NBT in the 1.20.2+ network form, where the root tag has a type but
+/// no name.
+pub fn parse_nbt_network(buf: &mut dyn Buf) -> Result<NbtTag, MinecraftParseError> {
+    let tag_type = parse_ubyte(buf)?;
+    if tag_type == TAG_END {
+        return Ok(NbtTag::End);
+    }
+    parse_payload(buf, tag_type, 0)
+}
+
+/// Parses NBT with a named root tag, as used by pre-1.20.2 packets and NBT
+/// files. The name has a 2-byte length prefix like every other NBT string.
+pub fn parse_nbt_named(buf: &mut dyn Buf) -> Result<(String, NbtTag), MinecraftParseError> {
+    let tag_type = parse_ubyte(buf)?;
+    if tag_type == TAG_END {
+        return Ok((String::new(), NbtTag::End));
+    }
+    let name = parse_nbt_string(buf)?;
+    Ok((name, parse_payload(buf, tag_type, 0)?))
+}
+
+/// Parses the payload of a tag whose type is already known, such as a bare
+/// NBT field of fixed type. There is no leading type byte or name, and
+/// `TAG_End` (0) has no payload, so it is rejected like an unknown type.
+pub fn parse_nbt_payload(buf: &mut dyn Buf, tag_type: u8) -> Result<NbtTag, MinecraftParseError> {
+    parse_payload(buf, tag_type, 0)
+}
+
+fn parse_len(buf: &mut dyn Buf) -> Result<usize, MinecraftParseError> {
+    let len = parse_int(buf)?;
+    if len < 0 {
+        return Err(MinecraftParseError::NegativeNbtLength(len));
+    }
+    Ok(len as usize)
+}
+
+fn parse_array<T>(
+    buf: &mut dyn Buf,
+    elem_size: usize,
+    get: impl Fn(&mut dyn Buf) -> T,
+) -> Result<Vec<T>, MinecraftParseError> {
+    let len = parse_len(buf)?;
+    // check up front so a forged length cannot trigger a huge allocation
+    if buf.remaining() / elem_size < len {
+        return Err(MinecraftParseError::UnexpectedEof);
+    }
+    Ok((0..len).map(|_| get(buf)).collect())
+}
+
+// NBT strings are Java's modified UTF-8: NUL is written as 0xC0 0x80 and
+// supplementary characters as surrogate pairs
+fn parse_nbt_string(buf: &mut dyn Buf) -> Result<String, MinecraftParseError> {
+    let len = parse_ushort(buf)? as usize;
+    if buf.remaining() < len {
+        return Err(MinecraftParseError::StringTooShort);
+    }
+
+    let bytes = buf.copy_to_bytes(len);
+    match str::from_utf8(&bytes) {
+        Ok(s) => Ok(s.to_string()),
+        Err(_) => decode_modified_utf8(&bytes),
+    }
+}
+
+fn decode_modified_utf8(bytes: &[u8]) -> Result<String, MinecraftParseError> {
+    let continuation = |i: usize| match bytes.get(i) {
+        Some(&b) if b & 0xC0 == 0x80 => Ok((b & 0x3F) as u16),
+        _ => Err(MinecraftParseError::InvalidNbtString),
+    };
+
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if b & 0x80 == 0 {
+            units.push(b as u16);
+            i += 1;
+        } else if b & 0xE0 == 0xC0 {
+            units.push(((b & 0x1F) as u16) << 6 | continuation(i + 1)?);
+            i += 2;
+        } else if b & 0xF0 == 0xE0 {
+            units.push(((b & 0x0F) as u16) << 12 | continuation(i + 1)? << 6 | continuation(i + 2)?);
+            i += 3;
+        } else {
+            return Err(MinecraftParseError::InvalidNbtString);
+        }
+    }
+    String::from_utf16(&units).map_err(|_| MinecraftParseError::InvalidNbtString)
+}
+
+fn parse_payload(buf: &mut dyn Buf, tag_type: u8, depth: usize) -> Result<NbtTag, MinecraftParseError> {
+    if depth > MAX_DEPTH {
+        return Err(MinecraftParseError::NbtTooDeep);
+    }
+
+    let tag = match tag_type {
+        TAG_BYTE => NbtTag::Byte(parse_byte(buf)?),
+        TAG_SHORT => NbtTag::Short(parse_short(buf)?),
+        TAG_INT => NbtTag::Int(parse_int(buf)?),
+        TAG_LONG => NbtTag::Long(parse_long(buf)?),
+        TAG_FLOAT => NbtTag::Float(parse_float(buf)?),
+        TAG_DOUBLE => NbtTag::Double(parse_double(buf)?),
+        TAG_BYTE_ARRAY => NbtTag::ByteArray(parse_array(buf, 1, |b| b.get_u8())?),
+        TAG_STRING => NbtTag::String(parse_nbt_string(buf)?),
+        TAG_LIST => {
+            let elem_type = parse_ubyte(buf)?;
+            let len = parse_len(buf)?;
+            if elem_type == TAG_END && len > 0 {
+                return Err(MinecraftParseError::InvalidNbtTag(TAG_END));
+            }
+            let mut elems = Vec::new();
+            for _ in 0..len {
+                elems.push(parse_payload(buf, elem_type, depth + 1)?);
+            }
+            NbtTag::List(elems)
+        }
+        TAG_COMPOUND => {
+            let mut entries = Vec::new();
+            loop {
+                let entry_type = parse_ubyte(buf)?;
+                if entry_type == TAG_END {
+                    break;
+                }
+                let name = parse_nbt_string(buf)?;
+                entries.push((name, parse_payload(buf, entry_type, depth + 1)?));
+            }
+            NbtTag::Compound(entries)
+        }
+        TAG_INT_ARRAY => NbtTag::IntArray(parse_array(buf, 4, |b| b.get_i32())?),
+        TAG_LONG_ARRAY => NbtTag::LongArray(parse_array(buf, 8, |b| b.get_i64())?),
+        other => return Err(MinecraftParseError::InvalidNbtTag(other)),
+    };
+    Ok(tag)
+}
+
+#[cfg(test)]
+mod tests {
+    mod parse_nbt_network {
+        use crate::nbt::*;
+
+        #[test]
+        fn parse_simple_compound() {
+            // {name: "Bananrama", age: 3s}
+            let mut buf = &b"\x0a\x08\x00\x04name\x00\x09Bananrama\x02\x00\x03age\x00\x03\x00"[..];
+            let val = parse_nbt_network(&mut buf).unwrap();
+            assert_eq!(val, NbtTag::Compound(vec![
+                ("name".to_string(), NbtTag::String("Bananrama".to_string())),
+                ("age".to_string(), NbtTag::Short(3)),
+            ]));
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn parse_nested_list() {
+            // {pos: [[1, 2], [3]], data: [I; -1]}
+            let mut buf = &b"\x0a\
+                \x09\x00\x03pos\x09\x00\x00\x00\x02\
+                    \x03\x00\x00\x00\x02\x00\x00\x00\x01\x00\x00\x00\x02\
+                    \x03\x00\x00\x00\x01\x00\x00\x00\x03\
+                \x0b\x00\x04data\x00\x00\x00\x01\xff\xff\xff\xff\
+                \x00"[..];
+            let val = parse_nbt_network(&mut buf).unwrap();
+            assert_eq!(val, NbtTag::Compound(vec![
+                ("pos".to_string(), NbtTag::List(vec![
+                    NbtTag::List(vec![NbtTag::Int(1), NbtTag::Int(2)]),
+                    NbtTag::List(vec![NbtTag::Int(3)]),
+                ])),
+                ("data".to_string(), NbtTag::IntArray(vec![-1])),
+            ]));
+        }
+
+        #[test]
+        fn parse_numeric_tags() {
+            let mut buf = &b"\x0a\
+                \x01\x00\x01b\xff\
+                \x04\x00\x01l\x00\x00\x00\x00\x00\x00\x00\x2a\
+                \x05\x00\x01f\x3f\x80\x00\x00\
+                \x06\x00\x01d\xbf\xf0\x00\x00\x00\x00\x00\x00\
+                \x07\x00\x02ba\x00\x00\x00\x02\x01\x02\
+                \x0c\x00\x02la\x00\x00\x00\x01\x00\x00\x00\x00\x00\x00\x00\x07\
+                \x00"[..];
+            let val = parse_nbt_network(&mut buf).unwrap();
+            assert_eq!(val, NbtTag::Compound(vec![
+                ("b".to_string(), NbtTag::Byte(-1)),
+                ("l".to_string(), NbtTag::Long(42)),
+                ("f".to_string(), NbtTag::Float(1.0)),
+                ("d".to_string(), NbtTag::Double(-1.0)),
+                ("ba".to_string(), NbtTag::ByteArray(vec![1, 2])),
+                ("la".to_string(), NbtTag::LongArray(vec![7])),
+            ]));
+        }
+
+        #[test]
+        fn parse_bare_string_root() {
+            let mut buf = &b"\x08\x00\x02hi"[..];
+            let val = parse_nbt_network(&mut buf).unwrap();
+            assert_eq!(val, NbtTag::String("hi".to_string()));
+        }
+
+        #[test]
+        fn parse_empty_root() {
+            let mut buf = &b"\x00"[..];
+            assert_eq!(parse_nbt_network(&mut buf).unwrap(), NbtTag::End);
+        }
+
+        #[test]
+        fn parse_modified_utf8() {
+            // "a\0" followed by U+1F600 as a surrogate pair
+            let mut buf = &b"\x08\x00\x09a\xc0\x80\xed\xa0\xbd\xed\xb8\x80"[..];
+            let val = parse_nbt_network(&mut buf).unwrap();
+            assert_eq!(val, NbtTag::String("a\0\u{1f600}".to_string()));
+        }
+
+        #[test]
+        fn reject_unknown_tag_type() {
+            let mut buf = &b"\x0a\x0d\x00\x01x\x00"[..];
+            let val = parse_nbt_network(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::InvalidNbtTag(13)));
+        }
+
+        #[test]
+        fn reject_unterminated_compound() {
+            let mut buf = &b"\x0a\x01\x00\x01b\x01"[..];
+            let val = parse_nbt_network(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+
+        #[test]
+        fn reject_forged_array_length() {
+            let mut buf = &b"\x0b\x7f\xff\xff\xff\x00\x00\x00\x01"[..];
+            let val = parse_nbt_network(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedEof));
+        }
+
+        #[test]
+        fn reject_negative_length() {
+            let mut buf = &b"\x09\x03\xff\xff\xff\xff"[..];
+            let val = parse_nbt_network(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::NegativeNbtLength(-1)));
+        }
+
+        #[test]
+        fn reject_deep_nesting() {
+            let mut packet = vec![0x09];
+            for _ in 0..=MAX_DEPTH + 1 {
+                packet.extend_from_slice(b"\x09\x00\x00\x00\x01");
+            }
+            let mut buf = &packet[..];
+            let val = parse_nbt_network(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::NbtTooDeep));
+        }
+    }
+    mod parse_nbt_payload {
+        use crate::nbt::*;
+
+        #[test]
+        fn parse_int() {
+            let mut buf = &b"\x00\x00\x01\x00\xff"[..];
+            let val = parse_nbt_payload(&mut buf, 3).unwrap();
+            assert_eq!(val, NbtTag::Int(256));
+            assert_eq!(buf, b"\xff");
+        }
+
+        #[test]
+        fn parse_string() {
+            let mut buf = &b"\x00\x05hello"[..];
+            let val = parse_nbt_payload(&mut buf, 8).unwrap();
+            assert_eq!(val, NbtTag::String("hello".to_string()));
+        }
+
+        #[test]
+        fn reject_end_type() {
+            let mut buf = &b"\x00"[..];
+            let val = parse_nbt_payload(&mut buf, 0).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::InvalidNbtTag(0)));
+        }
+
+        #[test]
+        fn reject_unknown_type() {
+            let mut buf = &b"\x00"[..];
+            let val = parse_nbt_payload(&mut buf, 13).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::InvalidNbtTag(13)));
+        }
+    }
+
+    mod parse_nbt_named {
+        use crate::nbt::*;
+
+        #[test]
+        fn parse_hello_world() {
+            // hello_world.nbt from the NBT specification
+            let mut buf = &b"\x0a\x00\x0bhello world\x08\x00\x04name\x00\x09Bananrama\x00"[..];
+            let (name, val) = parse_nbt_named(&mut buf).unwrap();
+            assert_eq!(name, "hello world");
+            assert_eq!(val, NbtTag::Compound(vec![
+                ("name".to_string(), NbtTag::String("Bananrama".to_string())),
+            ]));
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn parse_empty_root() {
+            let mut buf = &b"\x00"[..];
+            let (name, val) = parse_nbt_named(&mut buf).unwrap();
+            assert_eq!(name, "");
+            assert_eq!(val, NbtTag::End);
+        }
+
+        #[test]
+        fn name_length_is_not_varint() {
+            // a VarInt reader would take 0x00 as an empty name
+            let mut buf = &b"\x08\x00\x01k\x00\x01v"[..];
+            let (name, val) = parse_nbt_named(&mut buf).unwrap();
+            assert_eq!(name, "k");
+            assert_eq!(val, NbtTag::String("v".to_string()));
+        }
+
+        #[test]
+        fn parse_truncated_name() {
+            let mut buf = &b"\x0a\x00\x0bhello"[..];
+            let val = parse_nbt_named(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::StringTooShort));
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use base64::Engine;
+use bytes::Buf;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{parse_frame, parse_string_n, MinecraftParseError, TextComponent};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatusResponse {
+    version_name: String,
+    protocol: i32,
+    max_players: i32,
+    online_players: i32,
+    sample: Vec<PlayerSample>,
+    description: String,
+    favicon: Option<String>,
+}
+
+impl StatusResponse {
+    pub fn version_name(&self) -> &str {
+        &self.version_name
+    }
+
+    pub fn protocol(&self) -> i32 {
+        self.protocol
+    }
+
+    pub fn max_players(&self) -> i32 {
+        self.max_players
+    }
+
+    pub fn online_players(&self) -> i32 {
+        self.online_players
+    }
+
+    /// A few of the online players; empty when the server sends none.
+    pub fn sample(&self) -> &[PlayerSample] {
+        &self.sample
+    }
+
+    /// The MOTD flattened to plain text.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// The `data:image/png;base64,...` URI of the server icon, if any.
+    pub fn favicon(&self) -> Option<&str> {
+        self.favicon.as_deref()
+    }
+
+    /// Decodes the server icon to PNG bytes, or `None` when there is none.
+    pub fn favicon_png(&self) -> Result<Option<Vec<u8>>, MinecraftParseError> {
+        let favicon = match &self.favicon {
+            Some(favicon) => favicon,
+            None => return Ok(None),
+        };
+        let data = favicon
+            .strip_prefix("data:image/png;base64,")
+            .ok_or(MinecraftParseError::InvalidFavicon)?;
+        // older servers wrap the base64 text at 76 columns
+        let data: String = data.chars().filter(|c| !matches!(c, '\r' | '\n')).collect();
+        base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .map(Some)
+            .map_err(|_| MinecraftParseError::InvalidFavicon)
+    }
+}
+
+/// One `players.sample` entry. Names are kept verbatim, since servers often
+/// put formatted text here instead of real player names.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PlayerSample {
+    name: String,
+    id: String,
+}
+
+impl PlayerSample {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+#[derive(Deserialize)]
+struct RawStatus {
+    version: RawVersion,
+    // servers hiding their player count leave this out
+    #[serde(default)]
+    players: RawPlayers,
+    // a bare string or a chat component
+    #[serde(default)]
+    description: Value,
+    favicon: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawVersion {
+    name: String,
+    protocol: i32,
+}
+
+#[derive(Deserialize, Default)]
+struct RawPlayers {
+    max: i32,
+    online: i32,
+    #[serde(default)]
+    sample: Vec<PlayerSample>,
+}
+
+pub fn parse_status_response(buf: &mut dyn Buf) -> Result<StatusResponse, MinecraftParseError> {
+    parse_frame(buf, |id, body| {
+        if id != 0x00 {
+            return Err(MinecraftParseError::UnexpectedPacketId(id));
+        }
+        let json = parse_string_n(body)?;
+        let raw: RawStatus = serde_json::from_str(&json).map_err(MinecraftParseError::InvalidJson)?;
+
+        let description = TextComponent::from_value(&raw.description)?.to_plain_string();
+
+        Ok(StatusResponse {
+            version_name: raw.version.name,
+            protocol: raw.version.protocol,
+            max_players: raw.players.max,
+            online_players: raw.players.online,
+            sample: raw.players.sample,
+            description,
+            favicon: raw.favicon,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    mod parse_status_response {
+        use crate::*;
+
+        fn frame(json: &str) -> Vec<u8> {
+            PacketBuilder::new(0x00).string(json).build()
+        }
+
+        #[test]
+        fn parse_plain_description() {
+            let packet = frame(r#"{"version":{"name":"1.16.5","protocol":754},"players":{"max":20,"online":3},"description":"A Minecraft Server","favicon":"data:image/png;base64,AAAA"}"#);
+            let mut buf = &packet[..];
+            let val = parse_status_response(&mut buf).unwrap();
+            assert_eq!(val.version_name(), "1.16.5");
+            assert_eq!(val.protocol(), 754);
+            assert_eq!(val.max_players(), 20);
+            assert_eq!(val.online_players(), 3);
+            assert_eq!(val.description(), "A Minecraft Server");
+            assert_eq!(val.favicon(), Some("data:image/png;base64,AAAA"));
+            assert!(buf.is_empty());
+        }
+
+        #[test]
+        fn parse_component_description() {
+            let packet = frame(r#"{"version":{"name":"Paper 1.20.4","protocol":765},"players":{"max":100,"online":0},"description":{"text":"Hello ","extra":[{"text":"world","color":"gold"},"!"]}}"#);
+            let mut buf = &packet[..];
+            let val = parse_status_response(&mut buf).unwrap();
+            assert_eq!(val.description(), "Hello world!");
+            assert_eq!(val.favicon(), None);
+        }
+
+        #[test]
+        fn parse_player_sample() {
+            let packet = frame(r#"{"version":{"name":"1.20.4","protocol":765},"players":{"max":20,"online":2,"sample":[{"name":"Notch","id":"069a79f4-44e9-4726-a5be-fca90e38aaf5"},{"name":"\u00a7aWelcome!","id":"00000000-0000-0000-0000-000000000000"}]},"description":""}"#);
+            let mut buf = &packet[..];
+            let val = parse_status_response(&mut buf).unwrap();
+            assert_eq!(val.sample().len(), 2);
+            assert_eq!(val.sample()[0].name(), "Notch");
+            assert_eq!(val.sample()[0].id(), "069a79f4-44e9-4726-a5be-fca90e38aaf5");
+            assert_eq!(val.sample()[1].name(), "\u{a7}aWelcome!");
+        }
+
+        #[test]
+        fn parse_missing_sample() {
+            let packet = frame(r#"{"version":{"name":"1.20.4","protocol":765},"players":{"max":20,"online":0},"description":""}"#);
+            let mut buf = &packet[..];
+            let val = parse_status_response(&mut buf).unwrap();
+            assert!(val.sample().is_empty());
+        }
+
+        #[test]
+        fn parse_hidden_players() {
+            let packet = frame(r#"{"version":{"name":"1.20.4","protocol":765},"description":""}"#);
+            let mut buf = &packet[..];
+            let val = parse_status_response(&mut buf).unwrap();
+            assert_eq!(val.max_players(), 0);
+            assert_eq!(val.online_players(), 0);
+        }
+
+        #[test]
+        fn decode_favicon() {
+            // the 8-byte PNG signature
+            let packet = frame(r#"{"version":{"name":"1.20.4","protocol":765},"description":"","favicon":"data:image/png;base64,iVBORw0KGgo="}"#);
+            let val = parse_status_response(&mut &packet[..]).unwrap();
+            assert_eq!(val.favicon_png().unwrap().unwrap(), b"\x89PNG\r\n\x1a\n");
+        }
+
+        #[test]
+        fn decode_wrapped_favicon() {
+            let packet = frame(r#"{"version":{"name":"1.8.8","protocol":47},"description":"","favicon":"data:image/png;base64,iVBO\nRw0KGgo="}"#);
+            let val = parse_status_response(&mut &packet[..]).unwrap();
+            assert_eq!(val.favicon_png().unwrap().unwrap(), b"\x89PNG\r\n\x1a\n");
+        }
+
+        #[test]
+        fn decode_missing_favicon() {
+            let packet = frame(r#"{"version":{"name":"1.20.4","protocol":765},"description":""}"#);
+            let val = parse_status_response(&mut &packet[..]).unwrap();
+            assert_eq!(val.favicon_png().unwrap(), None);
+        }
+
+        #[test]
+        fn reject_malformed_favicon() {
+            let packet = frame(r#"{"version":{"name":"1.20.4","protocol":765},"description":"","favicon":"data:image/jpeg;base64,AAAA"}"#);
+            let val = parse_status_response(&mut &packet[..]).unwrap();
+            assert!(matches!(val.favicon_png(), Err(MinecraftParseError::InvalidFavicon)));
+
+            let packet = frame(r#"{"version":{"name":"1.20.4","protocol":765},"description":"","favicon":"data:image/png;base64,!!!"}"#);
+            let val = parse_status_response(&mut &packet[..]).unwrap();
+            assert!(matches!(val.favicon_png(), Err(MinecraftParseError::InvalidFavicon)));
+        }
+
+        #[test]
+        fn parse_malformed_json() {
+            let packet = frame(r#"{"version":"#);
+            let mut buf = &packet[..];
+            let val = parse_status_response(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::InvalidJson(_)));
+        }
+
+        #[test]
+        fn parse_wrong_packet_id() {
+            let mut buf = &b"\x01\x01"[..];
+            let val = parse_status_response(&mut buf).err().unwrap();
+            assert!(matches!(val, MinecraftParseError::UnexpectedPacketId(0x01)));
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! Builders for synthetic packets, so tests can describe a packet by its
+//! fields instead of as a hex literal.
+
+use alloc::vec::Vec;
+
+use crate::PacketBuilder;
+
+/// The handshake most tests start from: protocol 754 to 123.45.67.89:25565,
+/// asking to log in.
+pub const SAMPLE_HANDSHAKE: &[u8] = b"\x13\x00\xf2\x05\x0c123.45.67.89\x63\xdd\x02";
+
+/// Prefixes `id` and `body` with their combined length.
+pub fn build_frame(id: i32, body: &[u8]) -> Vec<u8> {
+    PacketBuilder::new(id).bytes(body).build()
+}
+
+/// `next_state` is written as is, so out-of-range values can be tested.
+pub fn build_handshake_bytes(protocol: i32, addr: &str, port: u16, next_state: i32) -> Vec<u8> {
+    PacketBuilder::new(0x00).varint(protocol).string(addr).ushort(port).varint(next_state).build()
+}
+
+#[cfg(test)]
+mod tests {
+    mod build_handshake_bytes {
+        use crate::test_util::*;
+
+        #[test]
+        fn match_sample_packet() {
+            let val = build_handshake_bytes(754, "123.45.67.89", 25565, 2);
+            assert_eq!(val, SAMPLE_HANDSHAKE);
+        }
+
+        #[test]
+        fn build_unknown_next_state() {
+            let val = build_handshake_bytes(754, "", 25565, 300);
+            assert_eq!(&val[val.len() - 2..], b"\xac\x02");
+        }
+    }
+
+    mod build_frame {
+        use crate::test_util::*;
+
+        #[test]
+        fn prefix_id_and_body() {
+            assert_eq!(build_frame(0x01, b"\x2a"), b"\x02\x01\x2a");
+        }
+    }
+}
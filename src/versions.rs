@@ -0,0 +1,86 @@
+// Release protocol numbers only; snapshots reuse numbers between releases and
+// are left out on purpose. When several releases share a protocol, the newest
+// one is named, or `x` when the whole minor line shares it.
+static PROTOCOL_NAMES: &[(i32, &str)] = &[
+    (47, "1.8.x"),
+    (107, "1.9"),
+    (108, "1.9.1"),
+    (109, "1.9.2"),
+    (110, "1.9.4"),
+    (210, "1.10.x"),
+    (315, "1.11"),
+    (316, "1.11.2"),
+    (335, "1.12"),
+    (338, "1.12.1"),
+    (340, "1.12.2"),
+    (393, "1.13"),
+    (401, "1.13.1"),
+    (404, "1.13.2"),
+    (477, "1.14"),
+    (480, "1.14.1"),
+    (485, "1.14.2"),
+    (490, "1.14.3"),
+    (498, "1.14.4"),
+    (573, "1.15"),
+    (575, "1.15.1"),
+    (578, "1.15.2"),
+    (735, "1.16"),
+    (736, "1.16.1"),
+    (751, "1.16.2"),
+    (753, "1.16.3"),
+    (754, "1.16.5"),
+    (755, "1.17"),
+    (756, "1.17.1"),
+    (757, "1.18.1"),
+    (758, "1.18.2"),
+    (759, "1.19"),
+    (760, "1.19.2"),
+    (761, "1.19.3"),
+    (762, "1.19.4"),
+    (763, "1.20.1"),
+    (764, "1.20.2"),
+    (765, "1.20.4"),
+    (766, "1.20.6"),
+    (767, "1.21.1"),
+    (768, "1.21.3"),
+    (769, "1.21.4"),
+    (770, "1.21.5"),
+    (771, "1.21.6"),
+    (772, "1.21.8"),
+    (773, "1.21.10"),
+];
+
+pub fn protocol_name(version: i32) -> Option<&'static str> {
+    PROTOCOL_NAMES
+        .binary_search_by_key(&version, |&(v, _)| v)
+        .ok()
+        .map(|i| PROTOCOL_NAMES[i].1)
+}
+
+#[cfg(test)]
+mod tests {
+    mod protocol_name {
+        use crate::versions::*;
+
+        #[test]
+        fn name_1_16_5() {
+            assert_eq!(protocol_name(754), Some("1.16.5"));
+        }
+
+        #[test]
+        fn name_1_8() {
+            assert_eq!(protocol_name(47), Some("1.8.x"));
+        }
+
+        #[test]
+        fn unknown_for_snapshot_number() {
+            assert_eq!(protocol_name(49), None);
+            assert_eq!(protocol_name(0x40000000 | 100), None);
+        }
+
+        #[test]
+        fn table_is_sorted() {
+            assert!(PROTOCOL_NAMES.windows(2).all(|w| w[0].0 < w[1].0));
+        }
+    }
+}
@@ -0,0 +1,88 @@
+// Drives the parsers over every short input and many mutated ones. A panic
+// anywhere fails the test, so passing shows malformed input only ever comes
+// back as an error.
+
+use minecraft_parser::*;
+
+// integration tests cannot reach `test_util`, so the sample is encoded here
+fn sample() -> Vec<u8> {
+    let mut out = Vec::new();
+    encode_handshake(&Handshake::new(754, "123.45.67.89", 25565, NextState::Login), &mut out);
+    out
+}
+
+fn parse_all(data: &[u8]) -> Result<Handshake, MinecraftParseError> {
+    let _ = parse_varint(&mut &data[..]);
+    let _ = parse_string_n(&mut &data[..]);
+    let _ = parse_handshake_safe(data);
+    parse_handshake(&mut &data[..])
+}
+
+// xorshift, so the sample is the same on every run
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+#[test]
+fn every_slice_up_to_3_bytes() {
+    // no handshake fits in fewer than 7 bytes
+    assert!(parse_all(&[]).is_err());
+    for a in 0..=255u8 {
+        assert!(parse_all(&[a]).is_err());
+        for b in 0..=255u8 {
+            assert!(parse_all(&[a, b]).is_err());
+            for c in 0..=255u8 {
+                assert!(parse_all(&[a, b, c]).is_err());
+            }
+        }
+    }
+}
+
+#[test]
+fn random_4_byte_slices() {
+    let mut state = 0x9e3779b97f4a7c15;
+    for _ in 0..1_000_000 {
+        let data = (next_random(&mut state) as u32).to_le_bytes();
+        assert!(parse_all(&data).is_err());
+    }
+}
+
+#[test]
+fn every_truncation_of_sample() {
+    let sample = sample();
+    for len in 0..sample.len() {
+        assert!(parse_all(&sample[..len]).is_err(), "prefix of {} bytes", len);
+    }
+    assert!(parse_all(&sample).is_ok());
+}
+
+#[test]
+fn every_single_byte_mutation_of_sample() {
+    let sample = sample();
+    let mut data = sample.clone();
+    for i in 0..data.len() {
+        for byte in 0..=255u8 {
+            data[i] = byte;
+            let _ = parse_all(&data);
+        }
+        data[i] = sample[i];
+    }
+}
+
+#[test]
+fn random_mutations_of_sample() {
+    let sample = sample();
+    let mut state = 0x2545f4914f6cdd1d;
+    for _ in 0..100_000 {
+        let mut data = sample.clone();
+        for _ in 0..1 + next_random(&mut state) % 4 {
+            let i = next_random(&mut state) as usize % data.len();
+            data[i] = next_random(&mut state) as u8;
+        }
+        let len = next_random(&mut state) as usize % (data.len() + 1);
+        let _ = parse_all(&data[..len]);
+    }
+}
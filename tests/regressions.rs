@@ -0,0 +1,52 @@
+// Inputs that once panicked or overflowed; each must now come back as an error.
+
+use minecraft_parser::*;
+
+#[test]
+fn empty_buffer() {
+    assert!(parse_handshake(&mut &b""[..]).is_err());
+    assert!(parse_varint(&mut &b""[..]).is_err());
+    assert!(parse_string_n(&mut &b""[..]).is_err());
+}
+
+#[test]
+fn lone_continuation_byte() {
+    assert!(parse_handshake(&mut &b"\x80"[..]).is_err());
+    assert!(parse_varint(&mut &b"\x80"[..]).is_err());
+    assert!(parse_string_n(&mut &b"\x80"[..]).is_err());
+}
+
+#[test]
+fn five_continuation_bytes() {
+    assert!(parse_handshake(&mut &b"\x80\x80\x80\x80\x80"[..]).is_err());
+    assert!(parse_varint(&mut &b"\x80\x80\x80\x80\x80"[..]).is_err());
+    assert!(parse_varint(&mut &b"\xff\xff\xff\xff\xff\x01"[..]).is_err());
+}
+
+#[test]
+fn overflowing_5th_byte() {
+    assert!(parse_varint(&mut &b"\xff\xff\xff\xff\x7f"[..]).is_err());
+}
+
+#[test]
+fn over_long_length_prefix() {
+    // declares far more than is present
+    assert!(parse_handshake(&mut &b"\xff\xff\xff\xff\x07\x00"[..]).is_err());
+    assert!(parse_handshake(&mut &b"\xff\xff\xff\xff\x0f\x00"[..]).is_err());
+    assert!(parse_string_n(&mut &b"\xff\xff\xff\xff\x07\x31"[..]).is_err());
+}
+
+#[test]
+fn length_prefix_without_body() {
+    assert!(parse_handshake(&mut &b"\x13"[..]).is_err());
+}
+
+#[test]
+fn handshake_cut_inside_port() {
+    assert!(parse_handshake(&mut &b"\x12\x00\xf2\x05\x0c123.45.67.89\x63"[..]).is_err());
+}
+
+#[test]
+fn handshake_with_zero_length() {
+    assert!(parse_handshake(&mut &b"\x00"[..]).is_err());
+}